@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use super::{
+    rules::{constant, div, mux, neg, product, sum, sum_plus_one, AnonKey, Evaluator, Rule},
+    select::Select,
+};
+
+/// An arithmetic expression over calculator keys.
+///
+/// Expressions are built with the usual operators and then lowered into [`Rule`]s, with every
+/// intermediate value getting its own anonymous node. For instance `1 + a + b * c` lowers into:
+/// - target: SumPlusOne\[a, anon0]
+/// - anon0: Prod\[b, c]
+#[derive(Clone)]
+pub enum Expr<K: Clone + Eq + Hash + 'static> {
+    Key(K),
+    Const(f32),
+    Sum(Vec<Expr<K>>),
+    Prod(Vec<Expr<K>>),
+    Neg(Box<Expr<K>>),
+    Div(Box<Expr<K>>, Box<Expr<K>>),
+    Call(&'static Evaluator<K>, Vec<Expr<K>>),
+}
+
+impl<K: Clone + Eq + Hash + 'static> Expr<K> {
+    pub fn key(key: impl Into<K>) -> Self {
+        Self::Key(key.into())
+    }
+
+    pub fn constant(val: f32) -> Self {
+        Self::Const(val)
+    }
+
+    /// Calls a named evaluator with the given arguments as its keys.
+    pub fn call(op: &'static Evaluator<K>, args: Vec<Self>) -> Self {
+        Self::Call(op, args)
+    }
+
+    /// Picks one expression per value of `T`, using the calcindex held by the `selector` key.
+    pub fn select<T: Select>(selector: impl Into<K>, f: impl Fn(T) -> Self) -> Self {
+        let mut args = vec![Self::key(selector)];
+        args.extend(T::VARIANTS.iter().map(|&v| f(v)));
+        Self::Call(&mux, args)
+    }
+
+    fn into_terms(self) -> Vec<Self> {
+        match self {
+            Self::Sum(terms) => terms,
+            e => vec![e],
+        }
+    }
+
+    fn into_factors(self) -> Vec<Self> {
+        match self {
+            Self::Prod(factors) => factors,
+            e => vec![e],
+        }
+    }
+}
+
+impl<K: AnonKey> Expr<K> {
+    /// Lowers the expression into rules so that `target` evaluates to it.
+    ///
+    /// Intermediate nodes get anonymous keys, numbered from `next_anon`, which is advanced past
+    /// every id used so that it can be shared between the expressions of one set of rules.
+    pub fn lower(self, target: K, next_anon: &mut usize, rules: &mut HashMap<K, Rule<K>>) {
+        let rule = match self {
+            Self::Key(key) => Rule::new(&sum, vec![key]),
+            Self::Const(val) => Rule::new(&constant, vec![K::constant(val)]),
+            Self::Sum(terms) => {
+                // Constants are folded together, and a lone +1 becomes a sum_plus_one node.
+                let mut offset = 0.0;
+                let mut rest = Vec::new();
+                for term in terms {
+                    match term {
+                        Self::Const(val) => offset += val,
+                        term => rest.push(term),
+                    }
+                }
+                let mut keys = Self::operands(rest, next_anon, rules);
+                if offset == 1.0 {
+                    Rule::new(&sum_plus_one, keys)
+                } else {
+                    if offset != 0.0 {
+                        keys.push(Self::Const(offset).operand(next_anon, rules));
+                    }
+                    Rule::new(&sum, keys)
+                }
+            }
+            Self::Prod(factors) => Rule::new(&product, Self::operands(factors, next_anon, rules)),
+            Self::Neg(e) => Rule::new(&neg, vec![e.operand(next_anon, rules)]),
+            Self::Div(n, d) => Rule::new(
+                &div,
+                vec![n.operand(next_anon, rules), d.operand(next_anon, rules)],
+            ),
+            Self::Call(op, args) => Rule::new(op, Self::operands(args, next_anon, rules)),
+        };
+        rules.insert(target, rule);
+    }
+
+    /// The key to read the value of this expression from, creating an anonymous node if needed.
+    fn operand(self, next_anon: &mut usize, rules: &mut HashMap<K, Rule<K>>) -> K {
+        match self {
+            Self::Key(key) => key,
+            e => {
+                let key = K::anon(*next_anon);
+                *next_anon += 1;
+                e.lower(key.clone(), next_anon, rules);
+                key
+            }
+        }
+    }
+
+    fn operands(
+        exprs: Vec<Self>,
+        next_anon: &mut usize,
+        rules: &mut HashMap<K, Rule<K>>,
+    ) -> Vec<K> {
+        exprs
+            .into_iter()
+            .map(|e| e.operand(next_anon, rules))
+            .collect()
+    }
+}
+
+impl<K: Clone + Eq + Hash + 'static> Add for Expr<K> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut terms = self.into_terms();
+        terms.extend(rhs.into_terms());
+        Self::Sum(terms)
+    }
+}
+impl<K: Clone + Eq + Hash + 'static> Sub for Expr<K> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+impl<K: Clone + Eq + Hash + 'static> Mul for Expr<K> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut factors = self.into_factors();
+        factors.extend(rhs.into_factors());
+        Self::Prod(factors)
+    }
+}
+impl<K: Clone + Eq + Hash + 'static> Div for Expr<K> {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        Self::Div(Box::new(self), Box::new(rhs))
+    }
+}
+impl<K: Clone + Eq + Hash + 'static> Neg for Expr<K> {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        match self {
+            Self::Neg(e) => *e,
+            Self::Const(val) => Self::Const(-val),
+            e => Self::Neg(Box::new(e)),
+        }
+    }
+}
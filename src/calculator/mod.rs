@@ -1,24 +1,41 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Debug},
+    hash::Hash,
+};
 
+pub mod expr;
 pub mod rules;
+pub mod select;
 use rules::Rules;
+use select::Select;
 
 pub struct Calculator<'a, K>
 where
     K: 'static + Clone + Eq + Hash,
 {
     values: HashMap<K, f32>,
+    /// The keys whose values were given to the calculator rather than computed. Invalidation
+    /// never removes them, so setting a key that has a rule overrides the rule until the key is
+    /// removed.
+    pinned: HashSet<K>,
     rules: &'a Rules<K>,
 }
 
 impl<'a, K: Clone + Eq + Hash> Calculator<'a, K> {
     pub fn from_components(values: HashMap<K, f32>, rules: &'a Rules<K>) -> Self {
-        Self { values, rules }
+        let pinned = values.keys().cloned().collect();
+        Self {
+            values,
+            pinned,
+            rules,
+        }
     }
 
     pub fn new(rules: &'a Rules<K>) -> Self {
         Self {
             values: HashMap::new(),
+            pinned: HashSet::new(),
             rules,
         }
     }
@@ -64,31 +81,43 @@ impl<'a, K: Clone + Eq + Hash> Calculator<'a, K> {
 
     /// Sets the value in the calculator, and removes the values for the parents so
     /// that the effects of setting this value will be seen in upstream calculations.
+    /// The value stays until it is set again or removed, even if the key has a rule.
     ///
     /// QUESTION - should children also be removed?
     /// Leaving them in invites a certain amount of confusion, but removing them could
     /// be annoying.
     pub fn set(&mut self, key: K, val: f32) {
+        self.pinned.insert(key.clone());
         if self.values.insert(key.clone(), val).is_some() {
             self.remove_parents(key);
         }
     }
 
+    /// Sets a selector key to the index of the value, see [`Select`].
+    pub fn set_select<T: Select>(&mut self, key: K, val: T) {
+        self.set(key, val.calcindex())
+    }
+
     /// Removes the value in the calculator, and removes the values for the parents
     /// to trigger a recalculation of the upstream keys.
     pub fn remove(&mut self, key: &K) -> Option<f32> {
+        self.pinned.remove(key);
         self.remove_parents(key.clone());
         self.values.remove(key)
     }
 
-    /// Removes the parents of the key passed in until arriving at an unset value.
-    /// Will not remove the key itself.
+    /// Removes the parents of the key passed in until arriving at an unset or pinned value,
+    /// following every rule that uses the key. Will not remove the key itself.
     ///
     /// Calling this effectively results in a recalculation now including this key.
-    fn remove_parents(&mut self, mut key: K) -> Option<()> {
-        loop {
-            key = self.rules.get_parent(&key)?.clone();
-            self.values.remove(&key)?;
+    fn remove_parents(&mut self, key: K) {
+        let mut stack = vec![key];
+        while let Some(key) = stack.pop() {
+            for parent in self.rules.get_parents(&key) {
+                if !self.pinned.contains(parent) && self.values.remove(parent).is_some() {
+                    stack.push(parent.clone());
+                }
+            }
         }
     }
 
@@ -97,6 +126,7 @@ impl<'a, K: Clone + Eq + Hash> Calculator<'a, K> {
     /// from the value that you place using this method. If there was a previous value, it will be
     /// returned to you.
     pub fn place(&mut self, key: K, val: f32) -> Option<f32> {
+        self.pinned.insert(key.clone());
         self.values.insert(key, val)
     }
 
@@ -104,9 +134,65 @@ impl<'a, K: Clone + Eq + Hash> Calculator<'a, K> {
     /// have already been calculated, their values will be used instead of recalculating from the value
     /// that you deleted using this method. If there was a previous value, it will be returned to you.
     pub fn delete(&mut self, key: &K) -> Option<f32> {
+        self.pinned.remove(key);
         self.values.remove(key)
     }
+
+    /// The value of the key if it was given to the calculator, rather than computed.
+    pub fn given(&self, key: &K) -> Option<f32> {
+        self.pinned.get(key).map(|k| self.values[k])
+    }
+}
+
+/// Keys with stable names, which calculator snapshots are saved under.
+pub trait SnapshotKey: Sized {
+    /// The name of the key, or `None` for keys that can't be saved, for instance because they are
+    /// numbered in the order the rules are built.
+    fn snapshot_name(&self) -> Option<String>;
+
+    /// Parses a name created by [`SnapshotKey::snapshot_name`].
+    fn from_snapshot_name(name: &str) -> Option<Self>;
+}
+
+impl<K: Clone + Eq + Hash + SnapshotKey> Calculator<'_, K> {
+    /// The values that were set in the calculator, by key name. Cached values are left out, so
+    /// snapshots don't depend on how the rules are written, and so are keys without a name.
+    pub fn snapshot(&self) -> BTreeMap<String, f32> {
+        self.pinned
+            .iter()
+            .filter_map(|k| Some((k.snapshot_name()?, self.values[k])))
+            .collect()
+    }
+
+    /// Clears the calculator, then sets every value of the snapshot. Fails without changing the
+    /// calculator if a name does not belong to any key.
+    pub fn restore(&mut self, snapshot: &BTreeMap<String, f32>) -> Result<(), UnknownKeyName> {
+        let values = snapshot
+            .iter()
+            .map(|(name, &val)| {
+                K::from_snapshot_name(name)
+                    .map(|k| (k, val))
+                    .ok_or_else(|| UnknownKeyName(name.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.values.clear();
+        self.pinned.clear();
+        for (key, val) in values {
+            self.set(key, val);
+        }
+        Ok(())
+    }
+}
+
+/// A name in a snapshot that doesn't belong to any key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownKeyName(pub String);
+impl fmt::Display for UnknownKeyName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key name `{}`", self.0)
+    }
 }
+impl std::error::Error for UnknownKeyName {}
 
 impl<K: Clone + Eq + Hash + Debug> Calculator<'_, K> {
     /// Debug prints the sheets current data
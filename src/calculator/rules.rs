@@ -11,43 +11,65 @@ use std::{collections::HashMap, hash::Hash};
 /// and tag 2 is equal to the product of tags 3 and 4
 pub struct Rules<K: Clone + Eq + Hash + 'static> {
     rules: HashMap<K, Rule<K>>,
-    parents: HashMap<K, K>,
+    parents: HashMap<K, Vec<K>>,
 }
 impl<K: Clone + Eq + Hash> Rules<K> {
     pub fn new(rules: HashMap<K, Rule<K>>) -> Self {
-        let parents = rules
-            .iter()
-            .flat_map(|(parent, rule)| rule.keys.iter().map(|key| (key.clone(), parent.clone())))
-            .collect();
+        let mut parents: HashMap<K, Vec<K>> = HashMap::new();
+        for (parent, rule) in rules.iter() {
+            for key in rule.keys.iter() {
+                parents.entry(key.clone()).or_default().push(parent.clone());
+            }
+        }
         Self { rules, parents }
     }
 
     pub fn get(&self, key: &K) -> Option<&Rule<K>> {
         self.rules.get(key)
     }
-    pub fn get_parent(&self, key: &K) -> Option<&K> {
-        self.parents.get(key)
+    /// Gets the keys of every rule that uses this key.
+    pub fn get_parents(&self, key: &K) -> &[K] {
+        self.parents.get(key).map(Vec::as_slice).unwrap_or(&[])
     }
 }
 
+/// A node evaluator. Receives the calculator and the keys of the rule, and computes the value of the node.
+///
+/// Evaluators need to be `Sync` so that rule sets can be stored in statics.
+pub type Evaluator<K> = dyn Fn(&mut Calculator<K>, &[K]) -> f32 + Sync;
+
 #[derive(Clone)]
 pub struct Rule<K: Clone + Eq + Hash + 'static> {
     keys: Vec<K>,
-    operation: &'static dyn Fn(&mut Calculator<K>, &[K]) -> f32,
+    operation: &'static Evaluator<K>,
 }
 impl<K: Clone + Eq + Hash + 'static> Rule<K> {
-    pub fn new(operation: &'static dyn Fn(&mut Calculator<K>, &[K]) -> f32, keys: Vec<K>) -> Self {
+    pub fn new(operation: &'static Evaluator<K>, keys: Vec<K>) -> Self {
         Self { keys, operation }
     }
 
     pub fn keys(&self) -> &[K] {
         &self.keys
     }
-    pub fn op(&self) -> &'static dyn Fn(&mut Calculator<K>, &[K]) -> f32 {
+    pub fn op(&self) -> &'static Evaluator<K> {
         self.operation
     }
 }
 
+/// Keys that are able to name the intermediate nodes generated when lowering an
+/// [`Expr`](super::expr::Expr) into rules.
+pub trait AnonKey: Clone + Eq + Hash + 'static {
+    /// The key of the anonymous node with the given id. Ids are unique within one set of rules.
+    fn anon(id: usize) -> Self;
+
+    /// A key that stands for a constant value. It never gets a value in the calculator,
+    /// it is only read by the [`constant`] evaluator.
+    fn constant(val: f32) -> Self;
+
+    /// The value of the key if it was created with [`AnonKey::constant`].
+    fn as_constant(&self) -> Option<f32>;
+}
+
 /// Sum node evaluator. All keys' values will be added together.
 pub fn sum<K: Clone + Eq + Hash + 'static>(calc: &mut Calculator<K>, keys: &[K]) -> f32 {
     keys.iter().map(|k| calc.get(k)).sum()
@@ -97,3 +119,17 @@ pub fn neg<K: Clone + Eq + Hash + 'static>(calc: &mut Calculator<K>, keys: &[K])
             .expect("neg nodes should be passed exactly one key"),
     )
 }
+
+/// Division node evaluator. The first node will be divided by the second.
+pub fn div<K: Clone + Eq + Hash + 'static>(calc: &mut Calculator<K>, keys: &[K]) -> f32 {
+    let num = calc.get(keys.first().expect("div nodes must have numerator first"));
+    let den = calc.get(keys.get(1).expect("div nodes must have denominator second"));
+    num / den
+}
+
+/// Constant node evaluator. Returns the value stored in the first key, see [`AnonKey::constant`].
+pub fn constant<K: AnonKey>(_calc: &mut Calculator<K>, keys: &[K]) -> f32 {
+    keys.first()
+        .and_then(AnonKey::as_constant)
+        .expect("constant nodes must have a constant key first")
+}
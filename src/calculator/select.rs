@@ -0,0 +1,86 @@
+/// Types whose values pick between the nodes of a mux.
+///
+/// The calcindex of a value is its position in [`Select::VARIANTS`], and the mux rules are
+/// generated from the same list (see [`Expr::select`](super::expr::Expr::select)), so the
+/// index stored in a selector key always lines up with the node it is meant to pick.
+///
+/// Implement it with [`impl_select!`] or [`impl_select_prepended!`], which generate both
+/// `VARIANTS` and an exhaustive `calcindex` from one list, so that leaving out a value doesn't
+/// compile.
+pub trait Select: Copy + PartialEq + 'static {
+    /// Every value of the type, in calcindex order.
+    const VARIANTS: &'static [Self];
+
+    /// Return the index used by the calculator for this value.
+    fn calcindex(&self) -> f32;
+
+    /// The value for an index used by the calculator, if there is one.
+    fn from_calcindex(index: f32) -> Option<Self> {
+        if index < 0.0 || index.fract() != 0.0 {
+            return None;
+        }
+        Self::VARIANTS.get(index as usize).copied()
+    }
+}
+
+/// Implements [`Select`] for an enum from its values in calcindex order, written without `Self::`.
+/// Values of the type that can never be selected, like a Swirl of Geo, are listed after
+/// `unselectable:` as patterns, and panic in `calcindex`.
+macro_rules! impl_select {
+    (
+        $ty:ty { $($variant:ident $(($($arg:tt)*))?),+ $(,)? }
+        $(unselectable: $($invalid:pat),+ $(,)?)?
+    ) => {
+        impl $crate::calculator::select::Select for $ty {
+            const VARIANTS: &'static [Self] = &[$(Self::$variant $(($($arg)*))?),+];
+
+            $crate::calculator::select::impl_select!(
+                @calcindex 0.0,
+                [$($(
+                    $invalid => unreachable!(concat!(stringify!($invalid), " can't be selected")),
+                )+)?]
+                $([Self::$variant $(($($arg)*))?])+
+            );
+        }
+    };
+    (@calcindex $index:expr, [$($arms:tt)*] [$($variant:tt)*] $($rest:tt)*) => {
+        $crate::calculator::select::impl_select!(
+            @calcindex $index + 1.0, [$($arms)* $($variant)* => $index,] $($rest)*
+        );
+    };
+    (@calcindex $index:expr, [$($arms:tt)*]) => {
+        fn calcindex(&self) -> f32 {
+            match *self {
+                $($arms)*
+            }
+        }
+    };
+}
+pub(crate) use impl_select;
+
+/// Implements [`Select`] for a type made of one value of its own followed by every value of
+/// another `Select` type wrapped in a variant, like `Option<T>`, where `None` comes first.
+macro_rules! impl_select_prepended {
+    ($ty:ty { $first:path, $($wrap:ident)::+($inner:ty) }) => {
+        impl $crate::calculator::select::Select for $ty {
+            const VARIANTS: &'static [Self] = &{
+                const INNER: &[$inner] = <$inner as $crate::calculator::select::Select>::VARIANTS;
+                let mut variants = [$first; INNER.len() + 1];
+                let mut i = 0;
+                while i < INNER.len() {
+                    variants[i + 1] = $($wrap)::+(INNER[i]);
+                    i += 1;
+                }
+                variants
+            };
+
+            fn calcindex(&self) -> f32 {
+                match *self {
+                    $first => 0.0,
+                    $($wrap)::+(v) => $crate::calculator::select::Select::calcindex(&v) + 1.0,
+                }
+            }
+        }
+    };
+}
+pub(crate) use impl_select_prepended;
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
-use super::rules::{product, sum, Rule, Rules};
+use super::expr::Expr;
+use super::rules::{product, sum, AnonKey, Rule, Rules};
 
 use super::Calculator;
 
@@ -52,3 +53,60 @@ fn calc_remove() {
     calc.remove(&1);
     assert_eq!(calc.get(&5), 12.0);
 }
+
+#[test]
+fn calc_set_shared() {
+    let calcrules = Rules::new(HashMap::from([
+        (2, Rule::new(&sum, vec![0, 1])),
+        (3, Rule::new(&product, vec![0, 1])),
+    ]));
+    let mut calc = Calculator::from_components(HashMap::from([(0, 2.0), (1, 3.0)]), &calcrules);
+    assert_eq!(calc.get(&2), 5.0);
+    assert_eq!(calc.get(&3), 6.0);
+
+    calc.set(0, 4.0);
+    assert_eq!(calc.get(&2), 7.0);
+    assert_eq!(calc.get(&3), 12.0);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum TestKey {
+    N(i32),
+    A(usize),
+    C(u32),
+}
+impl AnonKey for TestKey {
+    fn anon(id: usize) -> Self {
+        Self::A(id)
+    }
+    fn constant(val: f32) -> Self {
+        Self::C(val.to_bits())
+    }
+    fn as_constant(&self) -> Option<f32> {
+        match *self {
+            Self::C(bits) => Some(f32::from_bits(bits)),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn expr_lower() {
+    use TestKey::N;
+    let mut rules = HashMap::new();
+    let mut anon = 0;
+    let key = |n| Expr::key(N(n));
+    (Expr::constant(1.0) + key(0) * (key(1) - key(2)) / key(3)).lower(N(4), &mut anon, &mut rules);
+    (key(4) * Expr::constant(0.5) - Expr::constant(2.0)).lower(N(5), &mut anon, &mut rules);
+    let calcrules = Rules::new(rules);
+    let mut calc = Calculator::from_components(
+        HashMap::from([(N(0), 2.0), (N(1), 7.0), (N(2), 3.0), (N(3), 4.0)]),
+        &calcrules,
+    );
+    assert_eq!(calc.get(&N(4)), 3.0);
+    assert_eq!(calc.get(&N(5)), -0.5);
+
+    // Anonymous nodes are recalculated like any other node.
+    calc.set(N(2), 5.0);
+    assert_eq!(calc.get(&N(4)), 2.0);
+}
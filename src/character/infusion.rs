@@ -0,0 +1,145 @@
+//! Elemental infusions, which convert the Physical damage of some attacks to an element.
+
+use crate::{
+    calculator::Calculator,
+    damage::{Attribute, Category},
+    element::{Element, ElementalApplication},
+    equipment::weapon::WeaponType,
+    GCK, L,
+};
+
+use super::talent::Talent;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Infusion {
+    element: Element,
+    categories: Vec<Category>,
+    weapons: Option<Vec<WeaponType>>,
+    priority: i32,
+    own: bool,
+    overridable: bool,
+}
+impl Infusion {
+    /// An overridable infusion from another character, of normal, charged and plunging attacks
+    /// with any weapon, with priority 0.
+    pub fn new(element: Element) -> Self {
+        Self {
+            element,
+            categories: vec![
+                Category::NormalAttack,
+                Category::ChargedAttack,
+                Category::PlungeAttack,
+            ],
+            weapons: None,
+            priority: 0,
+            own: false,
+            overridable: true,
+        }
+    }
+
+    pub fn with_categories(mut self, categories: Vec<Category>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    /// Limits the infusion to characters wielding one of the weapons, like Chongyun's skill which
+    /// only infuses swords, claymores and polearms.
+    pub fn with_weapons(mut self, weapons: Vec<WeaponType>) -> Self {
+        self.weapons = Some(weapons);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Makes the infusion part of the character's own kit, like Diluc's burst, which wins over
+    /// every infusion from other characters.
+    pub fn own(mut self) -> Self {
+        self.own = true;
+        self
+    }
+
+    /// Makes the infusion win over the overridable infusions from other characters, whatever
+    /// their priority, like Chongyun's skill. Infusions of the character's own kit still win.
+    pub fn not_overridable(mut self) -> Self {
+        self.overridable = false;
+        self
+    }
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn applies_to(&self, category: Category, weapon: WeaponType) -> bool {
+        self.categories.contains(&category)
+            && self.weapons.as_ref().is_none_or(|w| w.contains(&weapon))
+    }
+}
+
+/// The infusions active on a character.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Infusions {
+    weapon: WeaponType,
+    active: Vec<Infusion>,
+}
+impl Infusions {
+    /// No infusions, on a character wielding the weapon.
+    pub fn new(weapon: WeaponType) -> Self {
+        Self {
+            weapon,
+            active: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, infusion: Infusion) -> Self {
+        self.add(infusion);
+        self
+    }
+
+    pub fn add(&mut self, infusion: Infusion) {
+        self.active.push(infusion);
+    }
+
+    /// The infusion that wins for attacks of the category. Infusions of the character's own kit
+    /// come first, then those that can't be overridden, then the highest priority, and then the
+    /// latest added.
+    pub fn winner(&self, category: Category) -> Option<&Infusion> {
+        self.active
+            .iter()
+            .filter(|i| i.applies_to(category, self.weapon))
+            .max_by_key(|i| (i.own, !i.overridable, i.priority))
+    }
+
+    /// The attribute a hit ends up with. Only Physical hits are infused.
+    pub fn resolve(&self, attribute: Attribute, category: Category) -> Attribute {
+        match (attribute, self.winner(category)) {
+            (Attribute::Physical, Some(infusion)) => infusion.element.into(),
+            _ => attribute,
+        }
+    }
+
+    /// The talent with its attribute resolved, and the application of its element, for use with
+    /// the [`damage_calculator`](crate::damage_calculator). The application is of 1 unit, the
+    /// gauge of infused normal attacks; the caller should replace it for talents that apply more.
+    /// Talents without an attribute or a category are left as they are.
+    pub fn infuse(&self, talent: &Talent) -> (Talent, Option<ElementalApplication>) {
+        let mut talent = talent.clone();
+        if let (Some(attribute), Some(category)) = (talent.attribute(), talent.category()) {
+            talent.set_attribute(self.resolve(attribute, category));
+        }
+        let elem_app = match talent.attribute() {
+            Some(Attribute::Elemental(e)) => Some(ElementalApplication::new(e, 1.0)),
+            _ => None,
+        };
+        (talent, elem_app)
+    }
+
+    /// Sets `L::Attribute` to the resolved attribute of the hit, which the reaction of the hit is
+    /// derived from, and `L::Category` to the category.
+    pub fn write_to(&self, calc: &mut Calculator<GCK>, attribute: Attribute, category: Category) {
+        calc.set_select(GCK::L(L::Attribute), self.resolve(attribute, category));
+        calc.set_select(GCK::L(L::Category), category);
+    }
+}
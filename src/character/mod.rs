@@ -1,6 +1,7 @@
 // TODO - Remove when actually using Characters
 #![allow(dead_code)]
 
+pub mod infusion;
 pub mod talent;
 use talent::TalentSheet;
 
@@ -29,3 +30,6 @@ enum Constellation {
     Five,
     Six,
 }
+
+#[cfg(test)]
+mod tests;
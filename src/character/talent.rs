@@ -64,6 +64,12 @@ impl Talent {
     pub fn category(&self) -> Option<Category> {
         self.category
     }
+    pub fn attribute(&self) -> Option<Attribute> {
+        self.attribute
+    }
+    pub fn set_attribute(&mut self, attribute: Attribute) {
+        self.attribute = Some(attribute);
+    }
     pub fn get_scalings(&self) -> &[Stat] {
         &self.scalings
     }
@@ -76,7 +82,7 @@ impl Talent {
         ]
         .into_iter()
         .filter(|x| x.is_some())
-        .chain(None)
+        .chain([None])
         .collect()
     }
 }
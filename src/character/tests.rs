@@ -0,0 +1,141 @@
+use crate::{
+    calculator::{select::Select, Calculator},
+    damage::{Attribute, Category, CritMode},
+    damage_calculator::evaluate_damage_instance,
+    element::{reaction::ElementalReaction, Aura, Element, GaugedAura},
+    equipment::weapon::WeaponType,
+    stats::{Stat, StatSheet, Type as StatType},
+    GCK, GI_RULES, L,
+};
+
+use super::{
+    infusion::{Infusion, Infusions},
+    talent::{Talent, ICD},
+};
+
+#[test]
+fn infusion_priority() {
+    let chongyun = Infusion::new(Element::Cryo)
+        .with_weapons(WeaponType::MELEE.to_vec())
+        .not_overridable();
+    let bennett = Infusion::new(Element::Pyro)
+        .with_categories(vec![Category::NormalAttack, Category::ChargedAttack])
+        .with_weapons(WeaponType::MELEE.to_vec())
+        .with_priority(1);
+    let infusions = Infusions::new(WeaponType::Sword).with(bennett.clone());
+
+    let physical = Attribute::Physical;
+    assert_eq!(
+        infusions.resolve(physical, Category::NormalAttack),
+        Element::Pyro.into()
+    );
+    assert_eq!(
+        infusions.resolve(physical, Category::PlungeAttack),
+        physical
+    );
+    // Only Physical damage is infused.
+    assert_eq!(
+        infusions.resolve(Element::Hydro.into(), Category::NormalAttack),
+        Element::Hydro.into()
+    );
+    // Nor are the attacks of other weapons.
+    let bow = Infusions::new(WeaponType::Bow).with(bennett.clone());
+    assert_eq!(bow.resolve(physical, Category::NormalAttack), physical);
+
+    // Chongyun's infusion can't be overridden by other characters, whatever their priority.
+    let infusions = infusions.with(chongyun.clone());
+    assert_eq!(
+        infusions.resolve(physical, Category::NormalAttack),
+        Element::Cryo.into()
+    );
+    let infusions = Infusions::new(WeaponType::Claymore)
+        .with(chongyun.clone())
+        .with(bennett.with_priority(10));
+    assert_eq!(
+        infusions.resolve(physical, Category::ChargedAttack),
+        Element::Cryo.into()
+    );
+
+    // Own infusions win over any other, and the latest wins ties.
+    let diluc = Infusion::new(Element::Pyro).own();
+    let infusions = Infusions::new(WeaponType::Claymore)
+        .with(diluc)
+        .with(chongyun.clone());
+    assert_eq!(
+        infusions.resolve(physical, Category::ChargedAttack),
+        Element::Pyro.into()
+    );
+    let infusions = Infusions::new(WeaponType::Polearm)
+        .with(Infusion::new(Element::Electro))
+        .with(Infusion::new(Element::Hydro));
+    assert_eq!(
+        infusions.resolve(physical, Category::NormalAttack),
+        Element::Hydro.into()
+    );
+}
+
+#[test]
+fn infused_reaction() {
+    let infusions = Infusions::new(WeaponType::Sword).with(Infusion::new(Element::Pyro));
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Cryo));
+    infusions.write_to(&mut calc, Attribute::Physical, Category::NormalAttack);
+    assert_eq!(
+        calc.get(&L::Attribute.into()),
+        Attribute::from(Element::Pyro).calcindex()
+    );
+    assert_eq!(
+        calc.get(&L::Reaction.into()),
+        Some(ElementalReaction::ForwardMelt).calcindex()
+    );
+
+    let talent = Talent::new(
+        Some(Category::NormalAttack),
+        Some(Attribute::Physical),
+        &ICD::STANDARD,
+        vec![],
+    );
+    let (infused, elem_app) = infusions.infuse(&talent);
+    assert_eq!(infused.attribute(), Some(Element::Pyro.into()));
+    assert_eq!(elem_app.as_ref().map(|e| e.element()), Some(Element::Pyro));
+}
+
+#[test]
+fn infused_legacy_damage() {
+    let infusions = Infusions::new(WeaponType::Sword).with(Infusion::new(Element::Pyro));
+    let stats = StatSheet::from([(StatType::Level, 90.0), (StatType::Atk, 1000.0)]);
+    let target_stats = StatSheet::from([(StatType::Level, 90.0)]);
+    let aura = GaugedAura::new(Aura::Cryo, 1.0, 0.0);
+    let talent = Talent::new(
+        Some(Category::NormalAttack),
+        Some(Attribute::Physical),
+        &ICD::STANDARD,
+        vec![Stat::new(StatType::Atk, 1.0)],
+    );
+    let (infused, elem_app) = infusions.infuse(&talent);
+    let damage = evaluate_damage_instance(
+        &stats,
+        &infused,
+        elem_app,
+        &target_stats,
+        Some(&aura),
+        CritMode::NonCrit,
+    );
+    // 1000 ATK against level 90 DEF with no RES, forward melted.
+    assert!((damage - 1000.0 * 0.5 * 2.0).abs() < 0.01);
+
+    // A character with another weapon isn't infused, so Physical damage doesn't melt.
+    let (talent, elem_app) = Infusions::new(WeaponType::Catalyst)
+        .with(Infusion::new(Element::Pyro).with_weapons(WeaponType::MELEE.to_vec()))
+        .infuse(&talent);
+    assert!(elem_app.is_none());
+    let damage = evaluate_damage_instance(
+        &stats,
+        &talent,
+        elem_app,
+        &target_stats,
+        Some(&aura),
+        CritMode::NonCrit,
+    );
+    assert!((damage - 1000.0 * 0.5).abs() < 0.01);
+}
@@ -1,10 +1,11 @@
-use crate::element::Element;
+use crate::{
+    calculator::select::{impl_select, impl_select_prepended},
+    element::Element,
+};
 
 /// Attribute of an attack.
-/// For the calculator:
-/// - Physical = 1
-/// - Elemental = 2-8
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// For the calculator, Physical comes first, followed by the elements in their own order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Attribute {
     Physical,
     Elemental(Element),
@@ -22,16 +23,9 @@ impl From<Element> for Attribute {
         Self::Elemental(value)
     }
 }
-impl Attribute {
-    pub fn calcindex(&self) -> f32 {
-        match *self {
-            Self::Physical => 0.0,
-            Self::Elemental(e) => 1.0 + e.calcindex(),
-        }
-    }
-}
+impl_select_prepended!(Attribute { Attribute::Physical, Attribute::Elemental(Element) });
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Category {
     NormalAttack,
     ChargedAttack,
@@ -39,14 +33,24 @@ pub enum Category {
     ElementalSkill,
     ElementalBurst,
 }
-impl Category {
-    pub fn calcindex(&self) -> f32 {
-        match *self {
-            Self::NormalAttack => 0.0,
-            Self::ChargedAttack => 1.0,
-            Self::PlungeAttack => 2.0,
-            Self::ElementalBurst => 3.0,
-            Self::ElementalSkill => 4.0,
-        }
-    }
+impl_select!(Category {
+    NormalAttack,
+    ChargedAttack,
+    PlungeAttack,
+    ElementalSkill,
+    ElementalBurst,
+});
+
+/// Whether damage is calculated as a crit, as a non crit, or as the average of the two weighted by
+/// crit rate. For the calculator, the average comes first, so that it is used when unset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CritMode {
+    NonCrit,
+    AvgCrit,
+    OnCrit,
 }
+impl_select!(CritMode {
+    AvgCrit,
+    NonCrit,
+    OnCrit
+});
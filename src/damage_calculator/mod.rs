@@ -1,9 +1,17 @@
+//! A function based interface to the damage formulas, working on stat sheets and talents. Every
+//! function is evaluated with [`GI_RULES`], so both interfaces always agree.
+
+pub use crate::damage::CritMode;
 use crate::{
+    calculator::{select::Select, Calculator},
     character::talent::Talent,
+    damage::Attribute,
     element::{
         reaction::ElementalReaction::{self, *},
         Aura, ElementalApplication, GaugedAura,
     },
+    gi_calculator::gi_rules::{B, GCK, GI_RULES, L, S},
+    level_multiplier::crystallize_shield_base,
     stats::{StatSheet, Type::*},
 };
 
@@ -21,11 +29,22 @@ impl ReactionEffect {
     }
 }
 
-pub enum CritMode {
-    NonCrit,
-    AvgCrit,
-    OnCrit,
-}
+/// Receives the intermediate values of a damage instance, see [`TRACED`].
+pub type TraceSink<'a> = &'a mut dyn FnMut(&GCK, f32);
+
+/// The nodes sent to the trace sink, in the order they are sent.
+pub const TRACED: [B; 10] = [
+    B::BaseDMG,
+    B::BaseDMGMult,
+    B::BaseDMGAdd,
+    B::BaseDMGFinal,
+    B::AmpRxnMult,
+    B::DMGBonusMult,
+    B::TargetDEFMult,
+    B::TargetRESMult,
+    B::CritMult,
+    B::DamageInstanceOutput,
+];
 
 pub fn evaluate_damage_instance(
     stats: &StatSheet,
@@ -35,62 +54,155 @@ pub fn evaluate_damage_instance(
     target_aura: Option<&GaugedAura>,
     crit_mode: CritMode,
 ) -> f32 {
-    let conds = talent.conditions_met();
-    let mut result = base_dmg(stats, talent)
-        * stats.sum_mults(conds.iter().map(|&x| BaseDMGMult(x)))
-        + stats.sum_mults(conds.iter().map(|&x| BaseDMGFlat(x)));
-    match rxn_effect(target_aura.map(|x| x.aura()), elem_app.as_ref(), stats) {
-        Some(ReactionEffect::Additive(val)) => result += val,
-        Some(ReactionEffect::Multiplicative(val)) => result *= val,
-        None => (),
+    evaluate_damage_instance_traced(
+        stats,
+        talent,
+        elem_app,
+        target_stats,
+        target_aura,
+        crit_mode,
+        None,
+    )
+}
+
+/// Same as [`evaluate_damage_instance`], also sending the intermediate values to the sink.
+pub fn evaluate_damage_instance_traced(
+    stats: &StatSheet,
+    talent: &Talent,
+    elem_app: Option<ElementalApplication>,
+    target_stats: &StatSheet,
+    target_aura: Option<&GaugedAura>,
+    crit_mode: CritMode,
+    sink: Option<TraceSink>,
+) -> f32 {
+    let mut calc = damage_instance_calculator(
+        stats,
+        talent,
+        elem_app,
+        target_stats,
+        target_aura,
+        crit_mode,
+    );
+    if let Some(sink) = sink {
+        for key in TRACED {
+            let key = key.into();
+            let val = calc.get(&key);
+            sink(&key, val);
+        }
     }
-    result
-        * stats.sum_mults(conds.iter().map(|&x| DMGMult(x)))
-        * def_mult(stats.get(Level), target_stats.get(Level))
-        * res_mult(target_stats.get(AttributeRES(elem_app.map(|x| x.element()).into())))
-        * crit_mult(stats.get(CritRate), stats.get(CritDmg), crit_mode)
+    calc.get(&B::DamageInstanceOutput.into())
+}
+
+/// Sets up a calculator for the damage instance. The hit has the attribute of the talent, or of
+/// the elemental application when the talent has none, and only reacts with the aura if it
+/// applies an element.
+pub fn damage_instance_calculator(
+    stats: &StatSheet,
+    talent: &Talent,
+    elem_app: Option<ElementalApplication>,
+    target_stats: &StatSheet,
+    target_aura: Option<&GaugedAura>,
+    crit_mode: CritMode,
+) -> Calculator<'static, GCK> {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(stats);
+    write_talent(&mut calc, stats, talent);
+    let attribute = talent
+        .attribute()
+        .unwrap_or(elem_app.as_ref().map(|x| x.element()).into());
+    calc.set_select(GCK::L(L::Attribute), attribute);
+    for (&typ, &val) in target_stats.data() {
+        match typ {
+            Level => calc.set(L::TargetLevel.into(), val),
+            AttributeRES(a) => calc.set(L::TargetAttributeRES(a).into(), val),
+            _ => (),
+        }
+    }
+    let aura = elem_app.and(target_aura).map(GaugedAura::aura);
+    calc.set_select(GCK::L(L::Aura), aura);
+    calc.set_select(GCK::L(L::CritMode), crit_mode);
+    calc
+}
+
+/// Sets the scalings and category of the talent. Scalings off stats that have no
+/// [`L::Scaling`] go to [`L::OtherScalingDMG`].
+fn write_talent(calc: &mut Calculator<GCK>, stats: &StatSheet, talent: &Talent) {
+    let mut other_scaling_dmg = 0.0;
+    for scaling in talent.get_scalings() {
+        let s = match scaling.typ() {
+            Atk => S::Atk,
+            MaxHP => S::MaxHP,
+            Def => S::Def,
+            ElementalMastery => S::EM,
+            typ => {
+                other_scaling_dmg += stats.get(typ) * scaling.val();
+                continue;
+            }
+        };
+        let key = L::Scaling(s).into();
+        let val = calc.get(&key) + scaling.val();
+        calc.set(key, val);
+    }
+    calc.set(L::OtherScalingDMG.into(), other_scaling_dmg);
+
+    match talent.category() {
+        Some(c) => calc.set_select(GCK::L(L::Category), c),
+        // Talents without a category get none of the category bonuses.
+        None => {
+            for b in [
+                B::CategoryBaseDMGMult,
+                B::CategoryBaseDMGAdd,
+                B::CategoryDMGBonusMult,
+                B::CategoryDEFIgnore,
+                B::CategoryCritRate,
+                B::CategoryCritDMG,
+            ] {
+                calc.set(b.into(), 0.0);
+            }
+        }
+    }
+}
+
+/// Evaluates a node of [`GI_RULES`] given the values of some other nodes.
+fn eval(inputs: &[(GCK, f32)], output: B) -> f32 {
+    let mut calc = Calculator::new(&GI_RULES);
+    for (key, val) in inputs {
+        calc.set(key.clone(), *val);
+    }
+    calc.get(&output.into())
 }
 
 /// Calculates the base damage given the stats and talent
 pub fn base_dmg(stats: &StatSheet, talent: &Talent) -> f32 {
-    let result = talent
-        .get_scalings()
-        .iter()
-        .map(|s| stats.get(s.typ()) * s.val())
-        .sum();
-    println!("base dmg: {result}");
-    result
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(stats);
+    write_talent(&mut calc, stats, talent);
+    calc.get(&B::BaseDMG.into())
 }
 
 /// Calculates the defense multiplier of the enemy
 pub fn def_mult(char_lvl: f32, enemy_lvl: f32) -> f32 {
-    let result = (char_lvl + 100.0) / (char_lvl + enemy_lvl + 200.0);
-    println!("def mult: {result}");
-    result
+    eval(
+        &[(Level.into(), char_lvl), (L::TargetLevel.into(), enemy_lvl)],
+        B::TargetDEFMult,
+    )
 }
 
 /// Calculates the resistance multiplier of the target
 pub fn res_mult(target_res: f32) -> f32 {
-    let result = if target_res < 0.0 {
-        1.0 - target_res / 2.0
-    } else if target_res < 0.75 {
-        1.0 - target_res
-    } else {
-        1.0 / (4.0 * target_res + 1.0)
-    };
-    println!("res mult: {result}");
-    result
+    eval(&[(B::TargetRESFinal.into(), target_res)], B::TargetRESMult)
 }
 
-/// Calculates the crit multiplier
+/// Calculates the crit multiplier. The crit rate is capped at 100% for the average.
 pub fn crit_mult(cr: f32, cd: f32, mode: CritMode) -> f32 {
-    let result = match mode {
-        CritMode::NonCrit => 1.0,
-        CritMode::AvgCrit => 1.0 + cr * cd,
-        CritMode::OnCrit => 1.0 + cd,
-    };
-    println!("crit mult: {result}");
-    result
+    eval(
+        &[
+            (B::TotalCritRate.into(), cr),
+            (B::TotalCritDMG.into(), cd),
+            (L::CritMode.into(), mode.calcindex()),
+        ],
+        B::CritMult,
+    )
 }
 
 /// Returns the reaction effect for the damage instance
@@ -99,61 +211,61 @@ pub fn rxn_effect(
     elem_app: Option<&ElementalApplication>,
     stats: &StatSheet,
 ) -> Option<ReactionEffect> {
-    let rxn = ElementalReaction::from_elements(target_aura?, elem_app?.element())?;
-    let result = match rxn {
-        ForwardVaporize | ForwardMelt => Some(ReactionEffect::Multiplicative(2.0)),
-        ReverseVaporize | ReverseMelt => Some(ReactionEffect::Multiplicative(1.5)),
-        Aggravate => Some(ReactionEffect::Additive(
-            1.15 * level_multiplier(stats.get(Level)),
-        )),
-        Spread => Some(ReactionEffect::Additive(
-            1.25 * level_multiplier(stats.get(Level)),
-        )),
-
-        _ => None,
+    let element = elem_app?.element();
+    let rxn = ElementalReaction::from_elements(target_aura?, element)?;
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(stats);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(element));
+    calc.set_select(GCK::L(L::Reaction), Some(rxn));
+    if rxn.is_amp_reaction() {
+        Some(ReactionEffect::Multiplicative(
+            calc.get(&B::AmpRxnMult.into()),
+        ))
+    } else if rxn.is_additive() {
+        Some(ReactionEffect::Additive(calc.get(&B::AddRxnDMGAdd.into())))
+    } else {
+        None
     }
-    .map(|re| {
-        re.map(|x| {
-            x * (1.0 + rxn_em_mult(rxn, stats.get(ElementalMastery)) + stats.get(RxnDMGMult(rxn)))
-        })
-    });
-    println!("reaction effect: {result:?}");
-    result
 }
 
 pub fn rxn_em_mult(rxn: ElementalReaction, em: f32) -> f32 {
     match rxn {
         ForwardMelt | ForwardVaporize | ReverseMelt | ReverseVaporize => amp_em_mult(em),
         Aggravate | Spread => add_em_mult(em),
-        Crystallize => cry_em_dmg_absorb(em),
+        Crystallize(_) => cry_em_dmg_absorb(em),
         _ => trans_em_mult(em),
     }
 }
 
 pub fn amp_em_mult(em: f32) -> f32 {
-    let result = 2.778 * em / (em + 1400.0);
-    println!("amplifying em mult: {result}");
-    result
+    eval(&[(ElementalMastery.into(), em)], B::AmpRxnEMMult)
 }
 
 pub fn trans_em_mult(em: f32) -> f32 {
-    let result = 16.0 * em / (em + 2000.0);
-    println!("transformative em mult: {result}");
-    result
+    eval(&[(ElementalMastery.into(), em)], B::TransRxnEMMult)
 }
 
 pub fn add_em_mult(em: f32) -> f32 {
-    let result = 5.0 * em / (em + 1200.0);
-    println!("additive em mult: {result}");
-    result
+    eval(&[(ElementalMastery.into(), em)], B::AddRxnEMMult)
 }
 
 pub fn cry_em_dmg_absorb(em: f32) -> f32 {
-    let result = 4.44 * em / (em + 1400.0);
-    println!("crystallize em mult: {result}");
-    result
+    eval(&[(ElementalMastery.into(), em)], B::CrystallizeEMMult)
+}
+
+/// The shield HP of the Crystallize shard, before shield strength and absorption, or `None` for
+/// the levels whose base shield HP is not known.
+pub fn crystallize_shield_hp(level: f32, em: f32) -> Option<f32> {
+    crystallize_shield_base(level)?;
+    Some(eval(
+        &[(Level.into(), level), (ElementalMastery.into(), em)],
+        B::CrystallizeShieldOutput,
+    ))
 }
 
-pub fn level_multiplier(_level: f32) -> f32 {
-    todo!("level multipliers")
+pub fn level_multiplier(level: f32) -> f32 {
+    eval(&[(Level.into(), level)], B::CharLevelMult)
 }
+
+#[cfg(test)]
+mod tests;
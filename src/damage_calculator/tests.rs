@@ -0,0 +1,310 @@
+use crate::{
+    calculator::select::Select,
+    character::talent::{Talent, ICD},
+    damage::{Attribute, Category},
+    element::{
+        reaction::ElementalReaction::{self, *},
+        Aura, Element, ElementalApplication, GaugedAura,
+    },
+    gi_calculator::gi_rules::{versions::RulesVersion, B, GCK},
+    level_multiplier::character_level_multiplier,
+    stats::{Condition, Stat, StatSheet, Type::*},
+};
+
+use super::{
+    crit_mult, evaluate_damage_instance, evaluate_damage_instance_traced, CritMode,
+    ReactionEffect::*, TRACED,
+};
+
+/// The damage calculator as it was before it was evaluated with the rules, without its
+/// `println!`s. It sums flat base DMG as a multiplier, which adds 1 to every hit, and takes the
+/// attribute of the hit from the elemental application only.
+fn legacy(
+    stats: &StatSheet,
+    talent: &Talent,
+    elem_app: Option<ElementalApplication>,
+    target_stats: &StatSheet,
+    target_aura: Option<&GaugedAura>,
+    crit_mode: CritMode,
+) -> f32 {
+    let conds = talent.conditions_met();
+    let base_dmg: f32 = talent
+        .get_scalings()
+        .iter()
+        .map(|s| stats.get(s.typ()) * s.val())
+        .sum();
+    let mut result = base_dmg * stats.sum_mults(conds.iter().map(|&x| BaseDMGMult(x)))
+        + stats.sum_mults(conds.iter().map(|&x| BaseDMGFlat(x)));
+    let em = stats.get(ElementalMastery);
+    let constants = RulesVersion::latest().constants;
+    let rxn = target_aura
+        .zip(elem_app.as_ref())
+        .and_then(|(aura, app)| ElementalReaction::from_elements(aura.aura(), app.element()));
+    let effect = rxn.and_then(|rxn| {
+        let level_mult = character_level_multiplier(stats.get(Level));
+        let (effect, em_mult) = match rxn {
+            ForwardVaporize | ForwardMelt => (Multiplicative(2.0), constants.amp_em.eval(em)),
+            ReverseVaporize | ReverseMelt => (Multiplicative(1.5), constants.amp_em.eval(em)),
+            Aggravate => (Additive(1.15 * level_mult), constants.additive_em.eval(em)),
+            Spread => (Additive(1.25 * level_mult), constants.additive_em.eval(em)),
+            _ => return None,
+        };
+        Some(effect.map(|x| x * (1.0 + em_mult + stats.get(RxnDMGMult(rxn)))))
+    });
+    match effect {
+        Some(Additive(val)) => result += val,
+        Some(Multiplicative(val)) => result *= val,
+        None => (),
+    }
+    let (char_lvl, enemy_lvl) = (stats.get(Level), target_stats.get(Level));
+    let target_res = target_stats.get(AttributeRES(elem_app.map(|x| x.element()).into()));
+    let res_mult = if target_res < 0.0 {
+        1.0 - target_res / 2.0
+    } else if target_res < 0.75 {
+        1.0 - target_res
+    } else {
+        1.0 / (4.0 * target_res + 1.0)
+    };
+    let cr: f32 = conds.iter().map(|&x| stats.get(CritRate(x))).sum();
+    let cd: f32 = conds.iter().map(|&x| stats.get(CritDmg(x))).sum();
+    let crit_mult = match crit_mode {
+        CritMode::NonCrit => 1.0,
+        CritMode::AvgCrit => 1.0 + cr * cd,
+        CritMode::OnCrit => 1.0 + cd,
+    };
+    result * stats.sum_mults(conds.iter().map(|&x| DMGMult(x))) * (char_lvl + 100.0)
+        / (char_lvl + enemy_lvl + 200.0)
+        * res_mult
+        * crit_mult
+}
+
+fn character() -> StatSheet {
+    let mut stats = StatSheet::from([
+        (Level, 90.0),
+        (MaxHP, 20000.0),
+        (Atk, 2500.0),
+        (Def, 800.0),
+        (ElementalMastery, 300.0),
+        (EnergyRecharge, 1.4),
+        (CritRate(None), 0.5),
+        (CritDmg(None), 1.2),
+        (DMGMult(None), 0.1),
+        (BaseDMGFlat(None), 150.0),
+        (RxnDMGMult(ForwardMelt), 0.15),
+        (RxnDMGMult(ReverseVaporize), 0.15),
+        (RxnDMGMult(Spread), 0.2),
+    ]);
+    // Distinct bonuses for every condition, so that picking the wrong one shows.
+    for (i, &c) in Category::VARIANTS.iter().enumerate() {
+        let c = Some(Condition::Category(c));
+        stats.add_stat(&Stat::new(DMGMult(c), 0.05 * i as f32));
+        stats.add_stat(&Stat::new(CritRate(c), 0.04 * i as f32));
+        stats.add_stat(&Stat::new(BaseDMGMult(c), 0.03 * i as f32));
+    }
+    for (i, &a) in Attribute::VARIANTS.iter().enumerate() {
+        let a = Some(Condition::Attribute(a));
+        stats.add_stat(&Stat::new(DMGMult(a), 0.1 * i as f32));
+        stats.add_stat(&Stat::new(CritDmg(a), 0.07 * i as f32));
+        stats.add_stat(&Stat::new(BaseDMGFlat(a), 20.0 * i as f32));
+    }
+    stats
+}
+
+fn enemy() -> StatSheet {
+    let mut stats = StatSheet::from([(Level, 100.0)]);
+    for (i, &a) in Attribute::VARIANTS.iter().enumerate() {
+        stats.add_stat(&Stat::new(AttributeRES(a), 0.1 * i as f32 - 0.3));
+    }
+    stats
+}
+
+/// Checks every element applied, including none, on every aura, against the legacy damage
+/// calculator. The talent has the attribute of the element, which is the only attribute the legacy
+/// calculator handled.
+#[test]
+fn parity_with_legacy() {
+    let (stats, target_stats) = (character(), enemy());
+    // Makes up for the 1 that the legacy calculator adds to every hit.
+    let mut legacy_stats = stats.clone();
+    legacy_stats.add_stat(&Stat::new(BaseDMGFlat(None), -1.0));
+    for element in [None]
+        .into_iter()
+        .chain(Element::VARIANTS.iter().copied().map(Some))
+    {
+        for &category in Category::VARIANTS {
+            let talent = Talent::new(
+                Some(category),
+                Some(element.into()),
+                &ICD::STANDARD,
+                vec![
+                    Stat::new(Atk, 1.5),
+                    Stat::new(ElementalMastery, 0.5),
+                    Stat::new(EnergyRecharge, 300.0),
+                ],
+            );
+            let elem_app = || element.map(|e| ElementalApplication::new(e, 1.0));
+            for aura in [None]
+                .into_iter()
+                .chain(Aura::VARIANTS.iter().copied().map(Some))
+            {
+                let aura = aura.map(|a| GaugedAura::new(a, 1.0, 0.0));
+                for &crit_mode in CritMode::VARIANTS {
+                    let expected = legacy(
+                        &legacy_stats,
+                        &talent,
+                        elem_app(),
+                        &target_stats,
+                        aura.as_ref(),
+                        crit_mode,
+                    );
+                    let actual = evaluate_damage_instance(
+                        &stats,
+                        &talent,
+                        elem_app(),
+                        &target_stats,
+                        aura.as_ref(),
+                        crit_mode,
+                    );
+                    assert!(
+                        (actual - expected).abs() < expected.abs() * 1e-5,
+                        "{element:?} {category:?} {aura:?} {crit_mode:?}: {actual} != {expected}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The legacy calculator took the attribute of the hit from the elemental application, so a Pyro
+/// talent without one was Physical and used Physical RES.
+#[test]
+fn talent_attribute_applies_without_elem_app() {
+    let talent = Talent::new(
+        Some(Category::ElementalSkill),
+        Some(Element::Pyro.into()),
+        &ICD::STANDARD,
+        vec![Stat::new(Atk, 1.0)],
+    );
+    let stats = StatSheet::from([(Level, 90.0), (Atk, 1000.0)]);
+    let target_stats = StatSheet::from([(Level, 90.0), (AttributeRES(Element::Pyro.into()), 0.5)]);
+    let aura = GaugedAura::new(Aura::Cryo, 1.0, 0.0);
+    let damage = |elem_app| {
+        evaluate_damage_instance(
+            &stats,
+            &talent,
+            elem_app,
+            &target_stats,
+            Some(&aura),
+            CritMode::NonCrit,
+        )
+    };
+    // 1000 * 0.5 DEF * 0.5 RES, and only a melt with an application.
+    assert_eq!(damage(None), 250.0);
+    assert_eq!(
+        damage(Some(ElementalApplication::new(Element::Pyro, 1.0))),
+        500.0
+    );
+}
+
+/// The legacy calculator didn't cap the crit rate, so an average crit could exceed a crit.
+#[test]
+fn crit_rate_capped() {
+    let talent = Talent::new(
+        Some(Category::NormalAttack),
+        Some(Attribute::Physical),
+        &ICD::STANDARD,
+        vec![Stat::new(Atk, 1.0)],
+    );
+    let stats = StatSheet::from([
+        (Level, 90.0),
+        (Atk, 1000.0),
+        (CritRate(None), 1.3),
+        (CritDmg(None), 1.0),
+    ]);
+    let target_stats = StatSheet::from([(Level, 90.0)]);
+    let damage =
+        |crit_mode| evaluate_damage_instance(&stats, &talent, None, &target_stats, None, crit_mode);
+    assert_eq!(damage(CritMode::AvgCrit), damage(CritMode::OnCrit));
+    assert_eq!(damage(CritMode::AvgCrit), 1000.0);
+    assert_eq!(crit_mult(1.3, 1.0, CritMode::AvgCrit), 2.0);
+    assert_eq!(crit_mult(-0.2, 1.0, CritMode::AvgCrit), 1.0);
+}
+
+/// Stats without a condition used to be left out, as `conditions_met` didn't include `None`.
+#[test]
+fn unconditional_stats_apply() {
+    let talent = Talent::new(
+        Some(Category::NormalAttack),
+        Some(Element::Pyro.into()),
+        &ICD::STANDARD,
+        vec![Stat::new(Atk, 1.0)],
+    );
+    assert!(talent.conditions_met().contains(&None));
+    let stats = StatSheet::from([
+        (Level, 90.0),
+        (Atk, 1000.0),
+        (CritDmg(None), 1.0),
+        (DMGMult(None), 0.5),
+    ]);
+    let target_stats = StatSheet::from([(Level, 90.0)]);
+    let damage =
+        |crit_mode| evaluate_damage_instance(&stats, &talent, None, &target_stats, None, crit_mode);
+    // 1000 * 1.5 DMG bonus * 0.5 DEF
+    assert_eq!(damage(CritMode::NonCrit), 750.0);
+    assert_eq!(damage(CritMode::OnCrit), 1500.0);
+}
+
+/// Flat base DMG used to be summed as a multiplier, which added 1 to every hit.
+#[test]
+fn flat_base_dmg_not_off_by_one() {
+    let talent = Talent::new(
+        Some(Category::ElementalSkill),
+        Some(Attribute::Physical),
+        &ICD::STANDARD,
+        vec![Stat::new(Atk, 1.0)],
+    );
+    let target_stats = StatSheet::from([(Level, 90.0)]);
+    let damage = |stats: &StatSheet| {
+        evaluate_damage_instance(stats, &talent, None, &target_stats, None, CritMode::NonCrit)
+    };
+    let mut stats = StatSheet::from([(Level, 90.0), (Atk, 1000.0)]);
+    assert_eq!(damage(&stats), 500.0);
+    stats.add_stat(&Stat::new(BaseDMGFlat(None), 100.0));
+    assert_eq!(damage(&stats), 550.0);
+}
+
+#[test]
+fn trace_sink() {
+    let talent = Talent::new(
+        Some(Category::ElementalSkill),
+        Some(Element::Hydro.into()),
+        &ICD::STANDARD,
+        vec![Stat::new(MaxHP, 0.2)],
+    );
+    let mut trace = Vec::new();
+    let output = evaluate_damage_instance_traced(
+        &character(),
+        &talent,
+        Some(ElementalApplication::new(Element::Hydro, 1.0)),
+        &enemy(),
+        Some(&GaugedAura::new(Aura::Pyro, 1.0, 0.0)),
+        CritMode::OnCrit,
+        Some(&mut |key: &GCK, val| trace.push((key.clone(), val))),
+    );
+    let keys: Vec<GCK> = TRACED.iter().cloned().map(GCK::from).collect();
+    assert_eq!(
+        trace.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>(),
+        keys
+    );
+    assert_eq!(trace.last().unwrap().1, output);
+
+    let value = |b: B| {
+        trace
+            .iter()
+            .find(|(k, _)| *k == b.clone().into())
+            .unwrap()
+            .1
+    };
+    assert_eq!(value(B::BaseDMG), 4000.0);
+    assert!((value(B::AmpRxnMult) - 2.0 * (1.0 + 2.78 * 300.0 / 1700.0)).abs() < 1e-5);
+}
@@ -1,6 +1,7 @@
-/// Note for calculator "mux": indexing starts at 1 and is in order
-/// that is written down here (order of nation's release)
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+use crate::calculator::select::{impl_select, impl_select_prepended};
+
+/// Listed in order of nation's release, which is also the order used by the calculator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Element {
     Anemo,
     Geo,
@@ -10,20 +11,17 @@ pub enum Element {
     Pyro,
     Cryo,
 }
-impl Element {
-    /// Return the index used by the calculator for each element.
-    pub fn calcindex(&self) -> f32 {
-        match *self {
-            Self::Anemo => 0.0,
-            Self::Geo => 1.0,
-            Self::Electro => 2.0,
-            Self::Dendro => 3.0,
-            Self::Hydro => 4.0,
-            Self::Pyro => 5.0,
-            Self::Cryo => 6.0,
-        }
-    }
-}
+impl_select!(Element {
+    Anemo,
+    Geo,
+    Electro,
+    Dendro,
+    Hydro,
+    Pyro,
+    Cryo,
+});
+// No element comes first, so that an unset element key means no element.
+impl_select_prepended!(Option<Element> { None, Some(Element) });
 
 #[derive(Clone, Debug)]
 pub struct ElementalApplication {
@@ -74,5 +72,33 @@ pub enum Aura {
     Burning,
     ElectroCharged,
 }
+impl_select!(Aura {
+    Electro,
+    Dendro,
+    Hydro,
+    Pyro,
+    Cryo,
+    Catalyzed,
+    BloomCore,
+    Frozen,
+    Burning,
+    ElectroCharged,
+});
+impl Aura {
+    /// The element absorbed from the aura by Swirl or Crystallize.
+    /// Electro-Charged targets have both Hydro and Electro, and Hydro is absorbed first. The
+    /// Electro reaction that follows on the same hit is not modelled.
+    pub fn absorbed_element(&self) -> Option<Element> {
+        match *self {
+            Self::Electro => Some(Element::Electro),
+            Self::Hydro | Self::ElectroCharged => Some(Element::Hydro),
+            Self::Pyro | Self::Burning => Some(Element::Pyro),
+            Self::Cryo | Self::Frozen => Some(Element::Cryo),
+            Self::Dendro | Self::Catalyzed | Self::BloomCore => None,
+        }
+    }
+}
+// No aura comes first, so that an unset aura key means the target has no aura.
+impl_select_prepended!(Option<Aura> { None, Some(Aura) });
 
 pub mod reaction;
@@ -1,10 +1,16 @@
 use super::{Aura, Element};
+use crate::{
+    calculator::select::{impl_select, impl_select_prepended},
+    damage::Attribute,
+};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ElementalReaction {
-    Swirl,
+    // Both carry the element absorbed from the aura, which is the element of the damage dealt by
+    // Swirl and of the shard created by Crystallize.
+    Swirl(Element),
 
-    Crystallize,
+    Crystallize(Element),
 
     Quicken,
     Aggravate,
@@ -26,41 +32,132 @@ pub enum ElementalReaction {
     ReverseVaporize,
 
     Freeze,
+    // Triggered by blunt attacks on Frozen targets rather than by an element.
+    Shatter,
 
     ForwardMelt,
     ReverseMelt,
 }
+impl_select!(ElementalReaction {
+    Swirl(Element::Electro),
+    Swirl(Element::Hydro),
+    Swirl(Element::Pyro),
+    Swirl(Element::Cryo),
+    Crystallize(Element::Electro),
+    Crystallize(Element::Hydro),
+    Crystallize(Element::Pyro),
+    Crystallize(Element::Cryo),
+    Quicken,
+    Aggravate,
+    Spread,
+    ElectroCharged,
+    Overloaded,
+    Superconduct,
+    Bloom,
+    Hyperbloom,
+    Burgeon,
+    Burning,
+    ForwardVaporize,
+    ReverseVaporize,
+    Freeze,
+    Shatter,
+    ForwardMelt,
+    ReverseMelt,
+}
+// Only Electro, Hydro, Pyro and Cryo can be swirled or crystallized.
+unselectable:
+    Self::Swirl(Element::Anemo | Element::Geo | Element::Dendro),
+    Self::Crystallize(Element::Anemo | Element::Geo | Element::Dendro),
+);
+// No reaction comes first, so that an unset reaction key means no reaction.
+impl_select_prepended!(Option<ElementalReaction> { None, Some(ElementalReaction) });
+
+/// Sets of reactions that bonuses can apply to as a whole, for instance every Vaporize.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReactionGroup {
+    Vaporize,
+    Melt,
+    Swirl,
+    Crystallize,
+    Amplifying,
+    Additive,
+    Transformative,
+}
+impl_select!(ReactionGroup {
+    Vaporize,
+    Melt,
+    Swirl,
+    Crystallize,
+    Amplifying,
+    Additive,
+    Transformative,
+});
+impl ReactionGroup {
+    pub fn contains(&self, reaction: ElementalReaction) -> bool {
+        use ElementalReaction as R;
+        match *self {
+            Self::Vaporize => matches!(reaction, R::ForwardVaporize | R::ReverseVaporize),
+            Self::Melt => matches!(reaction, R::ForwardMelt | R::ReverseMelt),
+            Self::Swirl => matches!(reaction, R::Swirl(_)),
+            Self::Crystallize => matches!(reaction, R::Crystallize(_)),
+            Self::Amplifying => reaction.is_amp_reaction(),
+            Self::Additive => reaction.is_additive(),
+            Self::Transformative => reaction.is_transformative(),
+        }
+    }
+}
+
 impl ElementalReaction {
-    pub fn amp_rxn_type_calcindex(&self) -> f32 {
+    /// Reactions that multiply the damage of the hit that triggered them. Their multipliers depend
+    /// on which element is the trigger, see
+    /// [`ReactionMults`](crate::gi_calculator::gi_rules::versions::ReactionMults).
+    pub fn is_amp_reaction(&self) -> bool {
+        matches!(
+            *self,
+            Self::ForwardVaporize | Self::ForwardMelt | Self::ReverseVaporize | Self::ReverseMelt
+        )
+    }
+
+    /// Reactions that add damage to the hit that triggered them, based on the level of the character.
+    pub fn is_additive(&self) -> bool {
+        matches!(*self, Self::Aggravate | Self::Spread)
+    }
+
+    /// Reactions that deal their own damage, based on the level of the character rather than on
+    /// the hit that triggered them.
+    pub fn is_transformative(&self) -> bool {
+        self.transformative_attribute().is_some()
+    }
+
+    /// The attribute of the damage dealt by transformative reactions, which decides the RES used.
+    pub fn transformative_attribute(&self) -> Option<Attribute> {
         match *self {
-            Self::ForwardVaporize | Self::ReverseVaporize => 0.0,
-            Self::ForwardMelt | Self::ReverseMelt => 1.0,
-            _ => 2.0,
+            Self::Swirl(e) => Some(e.into()),
+            Self::ElectroCharged => Some(Element::Electro.into()),
+            Self::Overloaded | Self::Burning => Some(Element::Pyro.into()),
+            Self::Superconduct => Some(Element::Cryo.into()),
+            Self::Bloom | Self::Hyperbloom | Self::Burgeon => Some(Element::Dendro.into()),
+            Self::Shatter => Some(Attribute::Physical),
+            _ => None,
+        }
+    }
+
+    /// The reaction triggered by a hit of the attribute on the aura, if any.
+    pub fn from_attribute(aura: Option<Aura>, attribute: Attribute) -> Option<Self> {
+        match attribute {
+            Attribute::Elemental(e) => Self::from_elements(aura?, e),
+            Attribute::Physical => None,
         }
     }
-    // pub fn is_amp_reaction(&self) -> bool {
-    //     match *self {
-    //         Self::ForwardVaporize
-    //         | Self::ReverseVaporize
-    //         | Self::ForwardMelt
-    //         | Self::ReverseMelt => true,
-    //         _ => false,
-    //     }
-    // }
 
     pub fn from_elements(aura: Aura, trigger: Element) -> Option<Self> {
         match (aura, trigger) {
-            (Aura::Electro, Element::Anemo)
-            | (Aura::Hydro, Element::Anemo)
-            | (Aura::Pyro, Element::Anemo)
-            | (Aura::Cryo, Element::Anemo) => Some(Self::Swirl),
+            (aura, Element::Anemo) => aura.absorbed_element().map(Self::Swirl),
+            (aura, Element::Geo) => aura.absorbed_element().map(Self::Crystallize),
 
-            (Aura::Electro, Element::Geo)
-            | (Aura::Hydro, Element::Geo)
-            | (Aura::Pyro, Element::Geo)
-            | (Aura::Cryo, Element::Geo) => Some(Self::Crystallize),
-
-            (Aura::Electro, Element::Dendro) => Some(Self::Quicken),
+            (Aura::Electro, Element::Dendro) | (Aura::Dendro, Element::Electro) => {
+                Some(Self::Quicken)
+            }
             (Aura::Catalyzed, Element::Electro) => Some(Self::Aggravate),
             (Aura::Catalyzed, Element::Dendro) => Some(Self::Spread),
 
@@ -88,7 +185,7 @@ impl ElementalReaction {
             (Aura::Hydro, Element::Cryo) | (Aura::Cryo, Element::Hydro) => Some(Self::Freeze),
 
             (Aura::Pyro, Element::Cryo) => Some(Self::ReverseMelt),
-            (Aura::Cryo, Element::Pyro) => Some(Self::ForwardMelt),
+            (Aura::Cryo | Aura::Frozen, Element::Pyro) => Some(Self::ForwardMelt),
 
             _ => None,
         }
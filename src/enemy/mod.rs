@@ -0,0 +1,126 @@
+//! Targets of attacks, with their level and resistances, and presets for common enemies.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calculator::{select::Select, Calculator},
+    damage::Attribute,
+    B, GCK, L,
+};
+
+/// The RES of most enemies to every attribute.
+pub const DEFAULT_RES: f32 = 0.1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Enemy {
+    level: f32,
+    #[serde(default = "default_res")]
+    default_res: f32,
+    /// RES to the attributes that don't use `default_res`.
+    #[serde(default)]
+    res: HashMap<Attribute, f32>,
+    /// Overrides the DEF multiplier of the damage formula, for enemies with unusual DEF.
+    #[serde(default)]
+    def_mult: Option<f32>,
+    /// Attributes that deal no damage to the enemy.
+    #[serde(default)]
+    immune: Vec<Attribute>,
+}
+
+fn default_res() -> f32 {
+    DEFAULT_RES
+}
+
+static PRESETS: LazyLock<BTreeMap<String, Enemy>> = LazyLock::new(|| {
+    load_presets(include_str!("presets.toml")).expect("bundled enemy presets should be valid")
+});
+
+/// Parses a table of enemies keyed by name, in the format of the bundled `presets.toml`.
+pub fn load_presets(text: &str) -> Result<BTreeMap<String, Enemy>, toml::de::Error> {
+    toml::from_str(text)
+}
+
+impl Enemy {
+    /// An enemy with `DEFAULT_RES` to every attribute.
+    pub fn new(level: f32) -> Self {
+        Self {
+            level,
+            default_res: DEFAULT_RES,
+            res: HashMap::new(),
+            def_mult: None,
+            immune: Vec::new(),
+        }
+    }
+
+    /// One of the bundled presets, see `presets.toml` for their names.
+    pub fn preset(name: &str) -> Option<Self> {
+        PRESETS.get(name).cloned()
+    }
+
+    /// The names of the bundled presets, in alphabetical order.
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.keys().map(String::as_str)
+    }
+
+    pub fn with_level(mut self, level: f32) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn with_res(mut self, attribute: Attribute, res: f32) -> Self {
+        self.res.insert(attribute, res);
+        self
+    }
+
+    pub fn with_def_mult(mut self, def_mult: f32) -> Self {
+        self.def_mult = Some(def_mult);
+        self
+    }
+
+    pub fn with_immunity(mut self, attribute: Attribute) -> Self {
+        self.immune.push(attribute);
+        self
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn def_mult(&self) -> Option<f32> {
+        self.def_mult
+    }
+
+    /// The RES to the attribute, which is infinite for immunities so that no damage is dealt.
+    pub fn res(&self, attribute: Attribute) -> f32 {
+        if self.immune.contains(&attribute) {
+            f32::INFINITY
+        } else {
+            self.res
+                .get(&attribute)
+                .copied()
+                .unwrap_or(self.default_res)
+        }
+    }
+
+    /// Sets the target leaves of the calculator to this enemy. RES shred is left as it is.
+    pub fn write_to(&self, calc: &mut Calculator<GCK>) {
+        calc.set(GCK::L(L::TargetLevel), self.level);
+        for &a in Attribute::VARIANTS {
+            calc.set(GCK::L(L::TargetAttributeRES(a)), self.res(a));
+        }
+        match self.def_mult {
+            Some(def_mult) => calc.set(GCK::B(B::TargetDEFMult), def_mult),
+            None => {
+                calc.remove(&GCK::B(B::TargetDEFMult));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,71 @@
+use crate::{
+    calculator::Calculator,
+    damage::Attribute,
+    element::Element,
+    stats::{StatSheet, Type as StatType},
+    B, GCK, GI_RULES, L, S,
+};
+
+use super::{load_presets, Enemy, DEFAULT_RES};
+
+#[test]
+fn bundled_presets() {
+    let names: Vec<_> = Enemy::preset_names().collect();
+    assert!(names.len() >= 15);
+    assert!(names.is_sorted());
+    assert!(names.contains(&"stormterror"));
+    let guard = Enemy::preset("ruin_guard").unwrap();
+    assert_eq!(guard.level(), 100.0);
+    assert_eq!(guard.res(Attribute::Physical), 0.7);
+    assert_eq!(guard.res(Element::Pyro.into()), DEFAULT_RES);
+
+    let slime = Enemy::preset("pyro_slime").unwrap();
+    assert_eq!(slime.res(Element::Pyro.into()), f32::INFINITY);
+    assert_eq!(slime.res(Element::Hydro.into()), DEFAULT_RES);
+    let hypostasis = Enemy::preset("geo_hypostasis").unwrap();
+    assert_eq!(hypostasis.res(Element::Geo.into()), f32::INFINITY);
+    assert_eq!(Enemy::preset("pyro_slyme"), None);
+}
+
+#[test]
+fn load_preset_table() {
+    let presets = load_presets(
+        r#"
+        [boss]
+        level = 95
+        default_res = 0.3
+        res = { Physical = -0.2, Geo = 0.7 }
+        def_mult = 0.4
+        "#,
+    )
+    .unwrap();
+    let boss = &presets["boss"];
+    assert_eq!(boss.res(Attribute::Physical), -0.2);
+    assert_eq!(boss.res(Element::Geo.into()), 0.7);
+    assert_eq!(boss.res(Element::Cryo.into()), 0.3);
+    assert_eq!(boss.def_mult(), Some(0.4));
+    assert!(load_presets("[boss]\nres = { Physical = 0.1 }").is_err());
+}
+
+#[test]
+fn write_to_calculator() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([(StatType::Atk, 1000.0)]));
+    calc.set(L::Scaling(S::Atk).into(), 1.0);
+    calc.set(StatType::Level.into(), 90.0);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Pyro));
+
+    Enemy::preset("hilichurl").unwrap().write_to(&mut calc);
+    assert_eq!(calc.get(&B::TargetRESMult.into()), 0.9);
+    assert!((calc.get(&B::TargetDEFMult.into()) - 190.0 / 390.0).abs() < 1e-6);
+
+    Enemy::preset("pyro_slime").unwrap().write_to(&mut calc);
+    assert_eq!(calc.get(&B::DamageInstanceOutput.into()), 0.0);
+
+    Enemy::new(90.0)
+        .with_res(Element::Pyro.into(), 0.5)
+        .with_def_mult(0.5)
+        .write_to(&mut calc);
+    assert_eq!(calc.get(&B::TargetRESMult.into()), 0.5);
+    assert_eq!(calc.get(&B::DamageInstanceOutput.into()), 250.0);
+}
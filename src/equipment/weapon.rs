@@ -3,6 +3,19 @@
 
 use crate::Ascension;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WeaponType {
+    Sword,
+    Claymore,
+    Polearm,
+    Bow,
+    Catalyst,
+}
+impl WeaponType {
+    /// The weapons of melee attacks, which most infusions from other characters are limited to.
+    pub const MELEE: [Self; 3] = [Self::Sword, Self::Claymore, Self::Polearm];
+}
+
 pub struct Weapon {
     level: f64,
     ascension: Ascension,
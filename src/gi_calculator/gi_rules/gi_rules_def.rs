@@ -1,16 +1,25 @@
-use std::{collections::HashMap, sync::LazyLock};
+use std::{collections::HashMap, iter, sync::LazyLock};
 
 use crate::{
     calculator::{
-        rules::{mux, mux0, mux1, neg, product, sum, sum_plus_one, Rule, Rules},
+        expr::Expr,
+        rules::{Rule, Rules},
+        select::Select,
         Calculator,
     },
-    damage::{Attribute, Category},
-    element::{reaction::ElementalReaction, Element},
-    stats::{Condition, Type as StatType},
+    damage::{Attribute, Category, CritMode},
+    element::{
+        reaction::{ElementalReaction, ReactionGroup},
+        Aura, Element,
+    },
+    level_multiplier::crystallize_shield_base,
+    stats::Type as StatType,
 };
 
-use super::{B, GCK, L, S};
+use super::{
+    versions::{FormulaConstants, RulesVersion},
+    B, GCK, L, S,
+};
 
 // Specialized calculator node evaluators
 
@@ -23,17 +32,30 @@ pub fn def_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
         keys.get(1)
             .expect("def_mult nodes must have enemy level second"),
     );
-    let def_reduct = calc.get(
+    let def_remaining = calc.get(
         keys.get(2)
-            .expect("def_mult nodes must have DEFReduct third"),
+            .expect("def_mult nodes must have DEFRemaining third"),
+    );
+    (c_level + 100.0) / (def_remaining * (e_level + 100.0) + (c_level + 100.0))
+}
+
+/// The fraction of the target's DEF left after DEF shred and DEF ignore, which stack
+/// multiplicatively. Each of them is capped at 100%, so the target never has negative DEF.
+pub fn def_remaining(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
+    keys.iter().map(|k| 1.0 - calc.get(k).min(1.0)).product()
+}
+
+/// The DEF multiplier of damage taken by the character, which only depends on the enemy level.
+pub fn incoming_def_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
+    let def = calc.get(
+        keys.first()
+            .expect("incoming_def_mult nodes must have character DEF first"),
     );
-    let def_ignore = calc.get(
-        keys.get(3)
-            .expect("def_mult nodes must have DEFIgnore fourth"),
+    let e_level = calc.get(
+        keys.get(1)
+            .expect("incoming_def_mult nodes must have enemy level second"),
     );
-    (c_level + 100.0)
-        / ((1.0 / (1.0 + def_reduct)) * (1.0 / (1.0 + def_ignore)) * (e_level + 100.0)
-            + (c_level + 100.0))
+    (5.0 * e_level + 500.0) / (def + 5.0 * e_level + 500.0)
 }
 
 pub fn res_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
@@ -50,14 +72,7 @@ pub fn res_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
     }
 }
 
-pub fn amp_rxn_em_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
-    let em = calc.get(
-        keys.first()
-            .expect("amp_rxn_em_mult nodes must have EM first"),
-    );
-    2.78 * em / (em + 1400.0)
-}
-
+/// Takes the crit mode as an optional third key, and returns the average when it is left out.
 pub fn crit_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
     let cr = calc.get(
         keys.first()
@@ -65,179 +80,436 @@ pub fn crit_mult(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
     );
     let cdmg = calc.get(
         keys.get(1)
-            .expect("crit_mult nodes must have TotalCritDMG first"),
+            .expect("crit_mult nodes must have TotalCritDMG second"),
+    );
+    let mode = keys
+        .get(2)
+        .and_then(|k| CritMode::from_calcindex(calc.get(k)))
+        .unwrap_or(CritMode::AvgCrit);
+    match mode {
+        CritMode::NonCrit => 1.0,
+        CritMode::AvgCrit => 1.0 + cr.clamp(0.0, 1.0) * cdmg,
+        CritMode::OnCrit => 1.0 + cdmg,
+    }
+}
+
+/// Looks up the level held by the first key in the table made of the other keys, rounding it down
+/// and clamping it to the levels in the table.
+pub fn level_table(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
+    let level = calc.get(
+        keys.first()
+            .expect("level_table nodes must have the level first"),
+    );
+    let index = (level.floor() as usize).clamp(1, keys.len() - 1);
+    calc.get(&keys[index])
+}
+
+/// NaN for the levels without data, so that every value calculated from it shows it is missing.
+pub fn crystallize_base(calc: &mut Calculator<GCK>, keys: &[GCK]) -> f32 {
+    let level = calc.get(
+        keys.first()
+            .expect("crystallize_base nodes must have character level first"),
     );
-    1.0 + cr * cdmg
+    crystallize_shield_base(level).unwrap_or(f32::NAN)
+}
+
+/// A `level_table` node over a table of level multipliers, for levels 1 to 100.
+fn level_mult(level: impl Into<GCK>, table: &[f32; 100]) -> Expr<GCK> {
+    let args = iter::once(Expr::key(level))
+        .chain(table.iter().map(|&val| Expr::constant(val)))
+        .collect();
+    Expr::call(&level_table, args)
+}
+
+/// How much of the damage of a hit a shield of the element absorbs. Shields absorb 250% of the
+/// damage of their own element, and Geo shields 150% of all damage.
+fn shield_absorption(element: Option<Element>) -> Expr<GCK> {
+    match element {
+        None => Expr::constant(1.0),
+        Some(Element::Geo) => Expr::constant(1.5),
+        Some(e) => Expr::select(L::IncomingAttribute, |a: Attribute| {
+            Expr::constant(if a == e.into() { 2.5 } else { 1.0 })
+        }),
+    }
 }
 
+/// The DMG bonus for the reaction, including the bonuses for every group it is part of.
+fn rxn_bonus(reaction: ElementalReaction) -> Expr<GCK> {
+    ReactionGroup::VARIANTS
+        .iter()
+        .filter(|g| g.contains(reaction))
+        .fold(Expr::key(StatType::RxnDMGMult(reaction)), |e, &g| {
+            e + Expr::key(StatType::RxnGroupDMGMult(g))
+        })
+}
+
+/// Builds a set of rules out of a `;` separated list of rule definitions. A definition is either:
+/// - A named node: `target => evaluator[key, key, ...]`
+/// - An expression: `target = expression`
+///
+/// Targets and keys are written as enum variants, for instance `B::BaseDMG` or
+/// `StatType::DMGMult(None)`, and converted into the key type with `Into`.
+///
+/// Expressions support `+ - * /`, parentheses, numeric literals, keys, and calls to named evaluators
+/// written as a single identifier, for instance `res_mult(B::TargetRESFinal)`. The arguments of a
+/// call can be expressions too. Every intermediate value is given an anonymous node.
+///
+/// `select(selector, |v: T| expression)` generates a mux over every value of a
+/// [`Select`](crate::calculator::select::Select) type, and `{ ... }` embeds a Rust block that
+/// evaluates to an [`Expr`].
+///
+/// `rule_gen!(@extend rules anon; ...)` adds the rules to an existing map of rules instead, given
+/// mutable references to the map and to the next free id for anonymous nodes.
 macro_rules! rule_gen {
-    ($($t:expr => $o:ident [$($k:expr),+]);*) => {
-        Rules::new(HashMap::from([
-            $(
-                ($t, Rule::new(&$o, vec![$($k),*])),
-            )*
-        ]))
+    (@extend $r:ident $a:ident; $($defs:tt)*) => {
+        rule_gen!(@def $r $a $($defs)*);
+    };
+
+    // Definitions, one at a time.
+    (@def $r:ident $a:ident) => {};
+    (@def $r:ident $a:ident
+        $t:ident :: $v:ident $(($($ta:tt)*))? => $o:ident [$($k:expr),+] $(; $($rest:tt)*)?
+    ) => {
+        $r.insert($t::$v$(($($ta)*))?.into(), Rule::new(&$o, vec![$($k.into()),+]));
+        rule_gen!(@def $r $a $($($rest)*)?);
     };
+    (@def $r:ident $a:ident $t:ident :: $v:ident $(($($ta:tt)*))? = $($rest:tt)*) => {
+        rule_gen!(@body $r $a [$t::$v$(($($ta)*))?] [] $($rest)*);
+    };
+
+    // Collecting the expression of a definition until reaching `;`. Tokens are moved four at a
+    // time when there is no `;` among them, as every step counts towards the recursion limit.
+    (@body $r:ident $a:ident [$($t:tt)+] [$($e:tt)+] $(; $($rest:tt)*)?) => {
+        Expr::lower(rule_gen!(@expr [] $($e)+), $($t)+.into(), $a, $r);
+        rule_gen!(@def $r $a $($($rest)*)?);
+    };
+    (@body $r:ident $a:ident [$($t:tt)+] [$($e:tt)*] $x:tt ; $($rest:tt)*) => {
+        rule_gen!(@body $r $a [$($t)+] [$($e)* $x] ; $($rest)*);
+    };
+    (@body $r:ident $a:ident [$($t:tt)+] [$($e:tt)*] $x:tt $y:tt ; $($rest:tt)*) => {
+        rule_gen!(@body $r $a [$($t)+] [$($e)* $x $y] ; $($rest)*);
+    };
+    (@body $r:ident $a:ident [$($t:tt)+] [$($e:tt)*] $x:tt $y:tt $z:tt ; $($rest:tt)*) => {
+        rule_gen!(@body $r $a [$($t)+] [$($e)* $x $y $z] ; $($rest)*);
+    };
+    (@body $r:ident $a:ident [$($t:tt)+] [$($e:tt)*] $x:tt $y:tt $z:tt $w:tt $($rest:tt)*) => {
+        rule_gen!(@body $r $a [$($t)+] [$($e)* $x $y $z $w] $($rest)*);
+    };
+    (@body $r:ident $a:ident [$($t:tt)+] [$($e:tt)*] $x:tt $($rest:tt)*) => {
+        rule_gen!(@body $r $a [$($t)+] [$($e)* $x] $($rest)*);
+    };
+
+    // Translating an expression into an Expr, leaving the operators for Rust to handle.
+    (@expr [$($o:tt)*]) => { $($o)* };
+    (@expr [$($o:tt)*] + $($rest:tt)*) => { rule_gen!(@expr [$($o)* +] $($rest)*) };
+    (@expr [$($o:tt)*] - $($rest:tt)*) => { rule_gen!(@expr [$($o)* -] $($rest)*) };
+    (@expr [$($o:tt)*] * $($rest:tt)*) => { rule_gen!(@expr [$($o)* *] $($rest)*) };
+    (@expr [$($o:tt)*] / $($rest:tt)*) => { rule_gen!(@expr [$($o)* /] $($rest)*) };
+    (@expr [$($o:tt)*] ($($i:tt)+) $($rest:tt)*) => {
+        rule_gen!(@expr [$($o)* (rule_gen!(@expr [] $($i)+))] $($rest)*)
+    };
+    (@expr [$($o:tt)*] {$($b:tt)*} $($rest:tt)*) => { rule_gen!(@expr [$($o)* {$($b)*}] $($rest)*) };
+    (@expr [$($o:tt)*] $l:literal $($rest:tt)*) => {
+        rule_gen!(@expr [$($o)* Expr::constant($l as f32)] $($rest)*)
+    };
+    (@expr [$($o:tt)*] select($s:expr, |$v:ident: $t:ty| $($body:tt)+) $($rest:tt)*) => {
+        rule_gen!(@expr [$($o)* Expr::select($s, |$v: $t| rule_gen!(@expr [] $($body)+))] $($rest)*)
+    };
+    (@expr [$($o:tt)*] $f:ident ($($args:tt)*) $($rest:tt)*) => {
+        rule_gen!(@expr [$($o)* Expr::call(&$f, rule_gen!(@args [] [] $($args)*))] $($rest)*)
+    };
+    (@expr [$($o:tt)*] $p:ident $($rest:tt)*) => { rule_gen!(@key [$($o)*] [$p] $($rest)*) };
+
+    // Collecting the path of a key, and its arguments if it has any.
+    (@key [$($o:tt)*] [$($p:tt)+] :: $s:ident $($rest:tt)*) => {
+        rule_gen!(@key [$($o)*] [$($p)+ :: $s] $($rest)*)
+    };
+    (@key [$($o:tt)*] [$($p:tt)+] ($($args:tt)*) $($rest:tt)*) => {
+        rule_gen!(@expr [$($o)* Expr::key($($p)+($($args)*))] $($rest)*)
+    };
+    (@key [$($o:tt)*] [$($p:tt)+] $($rest:tt)*) => {
+        rule_gen!(@expr [$($o)* Expr::key($($p)+)] $($rest)*)
+    };
+
+    // Splitting the arguments of an evaluator call on `,`.
+    (@args [$($d:expr),*] []) => { vec![$($d),*] };
+    (@args [$($d:expr),*] [$($c:tt)+]) => { vec![$($d,)* rule_gen!(@expr [] $($c)+)] };
+    (@args [$($d:expr),*] [$($c:tt)+] , $($rest:tt)*) => {
+        rule_gen!(@args [$($d,)* rule_gen!(@expr [] $($c)+)] [] $($rest)*)
+    };
+    (@args [$($d:expr),*] [$($c:tt)*] $x:tt $($rest:tt)*) => {
+        rule_gen!(@args [$($d),*] [$($c)* $x] $($rest)*)
+    };
+
+    ($($defs:tt)*) => {{
+        let mut rules = HashMap::new();
+        {
+            let (rules, anon) = (&mut rules, &mut 0);
+            rule_gen!(@def rules anon $($defs)*);
+        }
+        Rules::new(rules)
+    }};
 }
 
-// NOTE Clippy says that const items should not be interior mutable. Not quite sure what that means
-// to be honest, and I think that I should probably do some more research on what LazyLock is actually
-// doing here.
+/// The rules of the latest version, see [`RulesVersion`].
+pub static GI_RULES: LazyLock<&'static Rules<GCK>> =
+    LazyLock::new(|| RulesVersion::latest().rules());
 
-pub const GI_RULES: LazyLock<Rules<GCK>> = LazyLock::new(|| {
-    rule_gen!(
+/// Adds the rules shared by every version, using the formula constants of the version.
+pub(super) fn base_rules(
+    c: &FormulaConstants,
+    rules: &mut HashMap<GCK, Rule<GCK>>,
+    anon: &mut usize,
+) {
+    rule_gen!(@extend rules anon;
         // Top level Damage formula
-        GCK::B(B::DamageInstanceOutput) => product[
-            GCK::B(B::BaseDMGFinal),
-            GCK::B(B::DMGBonusMult),
-            GCK::B(B::TargetDEFMult),
-            GCK::B(B::TargetRESMult),
-            GCK::B(B::AmpRxnMult),
-            GCK::B(B::CritMult)
-        ];
+        B::DamageInstanceOutput = B::NonCritOutput * B::CritMult;
+        B::NonCritOutput = B::BaseDMGFinal
+            * B::DMGBonusMult
+            * B::TargetDEFMult
+            * B::TargetRESMult
+            * B::AmpRxnMult;
+        B::OnCritOutput = B::NonCritOutput
+            * crit_mult(
+                B::TotalCritRate,
+                B::TotalCritDMG,
+                { Expr::constant(CritMode::OnCrit.calcindex()) }
+            );
+        B::AvgCritOutput = B::NonCritOutput * crit_mult(B::TotalCritRate, B::TotalCritDMG);
 
         // Evaluating BaseDMGFinal
-        GCK::B(B::BaseDMGFinal) => sum[
-            GCK::B(B::BaseDMGPostMult),
-            GCK::L(L::BaseDMGAdd)
-        ];
-        GCK::B(B::BaseDMGPostMult) => product[
-            GCK::B(B::BaseDMG),
-            GCK::B(B::BaseDMGMult)
-        ];
-        // TODO - ADD Conditional BaseDMG modifiers
-        GCK::B(B::BaseDMGMult) => sum_plus_one[
-            GCK::L(L::Stat(StatType::BaseDMGMult(None)))
-        ];
-        GCK::B(B::BaseDMG) => sum[
-            GCK::B(B::EvalScaling(S::Atk)),
-            GCK::B(B::EvalScaling(S::MaxHP)),
-            GCK::B(B::EvalScaling(S::Def)),
-            GCK::B(B::EvalScaling(S::EM))
-        ];
-        GCK::B(B::EvalScaling(S::Atk)) => product[
-            GCK::L(L::Scaling(S::Atk)),
-            GCK::L(L::Stat(StatType::Atk))
-        ];
-        GCK::B(B::EvalScaling(S::MaxHP)) => product[
-            GCK::L(L::Scaling(S::MaxHP)),
-            GCK::L(L::Stat(StatType::MaxHP))
-        ];
-        GCK::B(B::EvalScaling(S::Def)) => product[
-            GCK::L(L::Scaling(S::Def)),
-            GCK::L(L::Stat(StatType::Def))
-        ];
-        GCK::B(B::EvalScaling(S::EM)) => product[
-            GCK::L(L::Scaling(S::EM)),
-            GCK::L(L::Stat(StatType::ElementalMastery))
-        ];
+        B::BaseDMGFinal = B::BaseDMGPostMult + B::BaseDMGAdd;
+        B::BaseDMGPostMult = B::BaseDMG * B::BaseDMGMult;
+        B::BaseDMGMult = 1
+            + StatType::BaseDMGMult(None)
+            + B::AttributeBaseDMGMult
+            + B::CategoryBaseDMGMult;
+        B::AttributeBaseDMGMult =
+            select(L::Attribute, |a: Attribute| StatType::BaseDMGMult(Some(a.into())));
+        B::CategoryBaseDMGMult =
+            select(L::Category, |c: Category| StatType::BaseDMGMult(Some(c.into())));
+        B::BaseDMGAdd = StatType::BaseDMGFlat(None)
+            + B::AttributeBaseDMGAdd
+            + B::CategoryBaseDMGAdd
+            + B::AddRxnDMGAdd
+            + L::BaseDMGAdd;
+        B::AttributeBaseDMGAdd =
+            select(L::Attribute, |a: Attribute| StatType::BaseDMGFlat(Some(a.into())));
+        B::CategoryBaseDMGAdd =
+            select(L::Category, |c: Category| StatType::BaseDMGFlat(Some(c.into())));
+        B::BaseDMG = B::EvalScaling(S::Atk)
+            + B::EvalScaling(S::MaxHP)
+            + B::EvalScaling(S::Def)
+            + B::EvalScaling(S::EM)
+            + L::OtherScalingDMG;
+        B::EvalScaling(S::Atk) = L::Scaling(S::Atk) * StatType::Atk;
+        B::EvalScaling(S::MaxHP) = L::Scaling(S::MaxHP) * StatType::MaxHP;
+        B::EvalScaling(S::Def) = L::Scaling(S::Def) * StatType::Def;
+        B::EvalScaling(S::EM) = L::Scaling(S::EM) * StatType::ElementalMastery;
+
+        // Evaluating the total stats, unless they are set directly
+        StatType::MaxHP = StatType::BaseHP * (1 + StatType::HPPercent) + StatType::FlatHP;
+        StatType::Atk = StatType::BaseAtk * (1 + StatType::AtkPercent) + StatType::FlatAtk;
+        StatType::Def = StatType::BaseDef * (1 + StatType::DefPercent) + StatType::FlatDef;
 
         // Evaluating DMGBonusMult
-        GCK::B(B::DMGBonusMult) => sum_plus_one[
-            GCK::L(L::Stat(StatType::DMGMult(None))),
-            GCK::B(B::AttributeDMGBonusMult),
-            GCK::B(B::CategoryDMGBonusMult),
-            GCK::L(L::TargetDMGBonusMult)
-        ];
-        GCK::B(B::AttributeDMGBonusMult) => mux[
-            GCK::L(L::Attribute),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Anemo)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Anemo)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Geo)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Electro)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Dendro)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Hydro)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Pyro)))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Attribute(Attribute::Elemental(Element::Cryo))))))
-        ];
-        GCK::B(B::CategoryDMGBonusMult) => mux[
-            GCK::L(L::Category),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Category(Category::NormalAttack))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Category(Category::ChargedAttack))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Category(Category::PlungeAttack))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Category(Category::ElementalSkill))))),
-            GCK::L(L::Stat(StatType::DMGMult(Some(Condition::Category(Category::ElementalBurst)))))
-        ];
+        B::DMGBonusMult = 1
+            + StatType::DMGMult(None)
+            + B::AttributeDMGBonusMult
+            + B::CategoryDMGBonusMult
+            + L::TargetDMGBonusMult;
+        B::AttributeDMGBonusMult =
+            select(L::Attribute, |a: Attribute| StatType::DMGMult(Some(a.into())));
+        B::CategoryDMGBonusMult =
+            select(L::Category, |c: Category| StatType::DMGMult(Some(c.into())));
 
         // Evaluating TargetDEFMult
-        GCK::B(B::TargetDEFMult) => def_mult[
-            GCK::L(L::Stat(StatType::Level)),
-            GCK::L(L::TargetLevel),
-            GCK::L(L::TargetDEFReduct),
-            GCK::B(B::TotalDEFIgnore)
-        ];
-        // todo - TotalDEFIgnore
+        B::TargetDEFMult = def_mult(StatType::Level, L::TargetLevel, B::TargetDEFRemaining);
+        B::TargetDEFRemaining = def_remaining(L::TargetDEFReduct, B::TotalDEFIgnore);
+        B::TotalDEFIgnore =
+            StatType::DefIgnore(None) + B::AttributeDEFIgnore + B::CategoryDEFIgnore;
+        B::AttributeDEFIgnore =
+            select(L::Attribute, |a: Attribute| StatType::DefIgnore(Some(a.into())));
+        B::CategoryDEFIgnore =
+            select(L::Category, |c: Category| StatType::DefIgnore(Some(c.into())));
 
         // Evaluating TargetRESMult
-        GCK::B(B::TargetRESMult) => res_mult[
-            GCK::B(B::TargetRESFinal)
-        ];
-        GCK::B(B::TargetRESFinal) => sum[
-            GCK::B(B::TargetAttributeRES),
-            GCK::B(B::TargetAttributeRESReductNeg)
-        ];
-        GCK::B(B::TargetAttributeRES) => mux[
-            GCK::L(L::Attribute),
-            GCK::L(L::TargetAttributeRES(Attribute::Physical)),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Anemo))),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Geo))),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Electro))),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Dendro))),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Hydro))),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Pyro))),
-            GCK::L(L::TargetAttributeRES(Attribute::Elemental(Element::Cryo)))
-        ];
-        GCK::B(B::TargetAttributeRESReductNeg) => neg[
-            GCK::B(B::TargetAttributeRESReduct)
-        ];
-        GCK::B(B::TargetAttributeRESReduct) => mux[
-            GCK::L(L::Attribute),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Physical)),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Anemo))),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Geo))),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Electro))),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Dendro))),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Hydro))),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Pyro))),
-            GCK::L(L::TargetAttributeRESReduct(Attribute::Elemental(Element::Cryo)))
-        ];
+        B::TargetRESMult = res_mult(B::TargetRESFinal);
+        B::TargetRESFinal = B::TargetAttributeRES - B::TargetAttributeRESReduct;
+        B::TargetAttributeRES = select(L::Attribute, |a: Attribute| L::TargetAttributeRES(a));
+        B::TargetAttributeRESReduct =
+            select(L::Attribute, |a: Attribute| L::TargetAttributeRESReduct(a));
+
+        // Evaluating the reaction of the hit, unless it is set directly
+        L::Reaction = select(L::Aura, |aura: Option<Aura>| {
+            Expr::select(L::Attribute, |a: Attribute| {
+                Expr::constant(ElementalReaction::from_attribute(aura, a).calcindex())
+            })
+        });
 
         // Evaluating AmpRxnMult
-        GCK::B(B::AmpRxnMult) => mux1[
-            GCK::L(L::AmpRxnType),
-            GCK::B(B::PotentialAmpRxnMult),
-            GCK::B(B::PotentialAmpRxnMult)
-        ];
-
-        GCK::B(B::PotentialAmpRxnMult) => product[
-            GCK::L(L::BaseAmpRxnMult),
-            GCK::B(B::AmpRxnTotalBonusMult)
-        ];
-        GCK::B(B::AmpRxnTotalBonusMult) => sum_plus_one[
-            GCK::B(B::AmpRxnEMMult),
-            GCK::B(B::AmpRxnBonusMult)
-        ];
-        GCK::B(B::AmpRxnEMMult) => amp_rxn_em_mult[
-            GCK::L(L::Stat(StatType::ElementalMastery))
-        ];
-        GCK::B(B::AmpRxnBonusMult) => mux0[
-            GCK::L(L::AmpRxnType),
-            GCK::L(L::Stat(StatType::RxnDMGMult(ElementalReaction::ForwardVaporize))),
-            GCK::L(L::Stat(StatType::RxnDMGMult(ElementalReaction::ForwardMelt)))
-        ];
+        B::AmpRxnMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(r) if r.is_amp_reaction() => Expr::key(B::PotentialAmpRxnMult),
+                _ => Expr::constant(1.0),
+            }
+        });
+
+        B::PotentialAmpRxnMult = L::BaseAmpRxnMult * B::AmpRxnTotalBonusMult;
+        L::BaseAmpRxnMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            let r = r.filter(ElementalReaction::is_amp_reaction);
+            Expr::constant(r.and_then(|r| c.reaction_mults.get(r)).unwrap_or(1.0))
+        });
+        B::AmpRxnTotalBonusMult = 1 + B::AmpRxnEMMult + B::AmpRxnBonusMult;
+        B::AmpRxnEMMult = { c.amp_em.expr() };
+        B::AmpRxnBonusMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(r) if r.is_amp_reaction() => rxn_bonus(r),
+                _ => Expr::constant(0.0),
+            }
+        });
+
+        // Evaluating AddRxnDMGAdd
+        B::AddRxnDMGAdd = B::AddRxnBaseDMG * B::AddRxnTotalBonusMult;
+        B::AddRxnBaseDMG = B::AddRxnBaseMult * B::CharLevelMult;
+        B::AddRxnBaseMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            let r = r.filter(ElementalReaction::is_additive);
+            Expr::constant(r.and_then(|r| c.reaction_mults.get(r)).unwrap_or(0.0))
+        });
+        B::AddRxnTotalBonusMult = 1 + B::AddRxnEMMult + B::AddRxnBonusMult;
+        B::AddRxnEMMult = { c.additive_em.expr() };
+        B::AddRxnBonusMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(r) if r.is_additive() => rxn_bonus(r),
+                _ => Expr::constant(0.0),
+            }
+        });
 
         // Evaluate CritMult
-        GCK::B(B::CritMult) => crit_mult[
-            GCK::B(B::TotalCritRate),
-            GCK::B(B::TotalCritDMG)
-        ];
-        GCK::B(B::TotalCritRate) => sum[
-            GCK::L(L::Stat(StatType::CritRate))
-            // TODO - ADD Conditional Crit Stats
-        ];
-        GCK::B(B::TotalCritDMG) => sum [
-            GCK::L(L::Stat(StatType::CritDmg))
-            // TODO - ADD Conditional Crit Stats
-        ]
-    )
-});
+        B::CritMult = crit_mult(B::TotalCritRate, B::TotalCritDMG, L::CritMode);
+        B::TotalCritRate =
+            StatType::CritRate(None) + B::AttributeCritRate + B::CategoryCritRate;
+        B::AttributeCritRate =
+            select(L::Attribute, |a: Attribute| StatType::CritRate(Some(a.into())));
+        B::CategoryCritRate =
+            select(L::Category, |c: Category| StatType::CritRate(Some(c.into())));
+        B::TotalCritDMG = StatType::CritDmg(None) + B::AttributeCritDMG + B::CategoryCritDMG;
+        B::AttributeCritDMG =
+            select(L::Attribute, |a: Attribute| StatType::CritDmg(Some(a.into())));
+        B::CategoryCritDMG =
+            select(L::Category, |c: Category| StatType::CritDmg(Some(c.into())));
+
+        // Transformative reactions are their own damage instances, which ignore DEF and can't crit
+        B::TransformativeRxnOutput = B::TransRxnBaseDMG
+            * B::TransRxnTotalBonusMult
+            * B::TransRxnRESMult
+            * B::TransRxnCritMult
+            * B::TransRxnAmpMult;
+        B::TransRxnBaseDMG = B::TransRxnBaseMult * B::CharLevelMult;
+        B::CharLevelMult = { level_mult(StatType::Level, c.char_level_mults) };
+        B::EnemyLevelMult = { level_mult(L::TargetLevel, c.enemy_level_mults) };
+        B::TransRxnBaseMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            let r = r.filter(ElementalReaction::is_transformative);
+            Expr::constant(r.and_then(|r| c.reaction_mults.get(r)).unwrap_or(0.0))
+        });
+        B::TransRxnTotalBonusMult = 1 + B::TransRxnEMMult + B::TransRxnBonusMult;
+        B::TransRxnEMMult = { c.transformative_em.expr() };
+        B::TransRxnBonusMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(r) if r.is_transformative() => rxn_bonus(r),
+                _ => Expr::constant(0.0),
+            }
+        });
+        B::TransRxnCritMult = crit_mult(B::TransRxnCritRate, B::TransRxnCritDMG, L::CritMode);
+        B::TransRxnCritRate = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(r) if r.is_transformative() => Expr::key(StatType::RxnCritRate(r)),
+                _ => Expr::constant(0.0),
+            }
+        });
+        B::TransRxnCritDMG = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(r) if r.is_transformative() => Expr::key(StatType::RxnCritDmg(r)),
+                _ => Expr::constant(0.0),
+            }
+        });
+        // Swirl deals damage of the element it absorbed, which the aura of the target hit, not
+        // necessarily the one swirled, can amplify
+        B::TransRxnAmpMult = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r {
+                Some(ElementalReaction::Swirl(e)) => Expr::select(L::Aura, |aura: Option<Aura>| {
+                    match aura.and_then(|a| ElementalReaction::from_elements(a, e)) {
+                        Some(amp) if amp.is_amp_reaction() => {
+                            let bonus = Expr::key(B::AmpRxnEMMult) + rxn_bonus(amp);
+                            Expr::constant(c.reaction_mults.get(amp).unwrap_or(1.0))
+                                * (Expr::constant(1.0) + bonus)
+                        }
+                        _ => Expr::constant(1.0),
+                    }
+                }),
+                _ => Expr::constant(1.0),
+            }
+        });
+        B::TransRxnRESMult = res_mult(B::TransRxnRESFinal);
+        B::TransRxnRESFinal = select(L::Reaction, |r: Option<ElementalReaction>| {
+            match r.and_then(|r| r.transformative_attribute()) {
+                Some(a) => Expr::key(L::TargetAttributeRES(a))
+                    - Expr::key(L::TargetAttributeRESReduct(a)),
+                None => Expr::constant(0.0),
+            }
+        });
+
+        // Heals
+        B::HealOutput = (B::HealBase + L::FlatHeal) * B::HealBonusMult;
+        B::HealBase = B::EvalHealScaling(S::Atk)
+            + B::EvalHealScaling(S::MaxHP)
+            + B::EvalHealScaling(S::Def)
+            + B::EvalHealScaling(S::EM);
+        B::EvalHealScaling(S::Atk) = L::HealScaling(S::Atk) * StatType::Atk;
+        B::EvalHealScaling(S::MaxHP) = L::HealScaling(S::MaxHP) * StatType::MaxHP;
+        B::EvalHealScaling(S::Def) = L::HealScaling(S::Def) * StatType::Def;
+        B::EvalHealScaling(S::EM) = L::HealScaling(S::EM) * StatType::ElementalMastery;
+        B::HealBonusMult = 1 + StatType::HealingBonus + L::TargetIncomingHealingBonus;
+
+        // Shields
+        B::ShieldOutput = (B::ShieldBase + L::FlatShield) * B::ShieldStrengthMult;
+        B::ShieldBase = B::EvalShieldScaling(S::Atk)
+            + B::EvalShieldScaling(S::MaxHP)
+            + B::EvalShieldScaling(S::Def)
+            + B::EvalShieldScaling(S::EM);
+        B::EvalShieldScaling(S::Atk) = L::ShieldScaling(S::Atk) * StatType::Atk;
+        B::EvalShieldScaling(S::MaxHP) = L::ShieldScaling(S::MaxHP) * StatType::MaxHP;
+        B::EvalShieldScaling(S::Def) = L::ShieldScaling(S::Def) * StatType::Def;
+        B::EvalShieldScaling(S::EM) = L::ShieldScaling(S::EM) * StatType::ElementalMastery;
+        B::ShieldStrengthMult = 1 + StatType::ShieldStrength;
+        B::ShieldEffectiveHP = B::ShieldOutput * B::ShieldAbsorptionMult;
+        B::ShieldAbsorptionMult =
+            select(L::ShieldElement, |e: Option<Element>| { shield_absorption(e) });
+
+        // Crystallize shards are shields of the absorbed element
+        B::CrystallizeShieldOutput = B::CrystallizeShieldBase
+            * (1 + B::CrystallizeEMMult)
+            * B::ShieldStrengthMult;
+        B::CrystallizeShieldBase = crystallize_base(StatType::Level);
+        B::CrystallizeEMMult = { c.crystallize_em.expr() };
+        B::CrystallizeShieldEffectiveHP =
+            B::CrystallizeShieldOutput * B::CrystallizeAbsorptionMult;
+        B::CrystallizeAbsorptionMult =
+            select(L::AbsorbedElement, |e: Option<Element>| { shield_absorption(e) });
+        L::AbsorbedElement = select(L::Aura, |a: Option<Aura>| {
+            Expr::constant(a.and_then(|a| a.absorbed_element()).calcindex())
+        });
+
+        // Damage taken by the character, from an enemy of level TargetLevel
+        B::IncomingDMGOutput = B::IncomingBaseDMG * B::IncomingDMGMult;
+        B::IncomingBaseDMG = L::IncomingScaling * B::EnemyLevelMult;
+        B::IncomingDMGMult = B::IncomingDEFMult * B::IncomingRESMult * B::IncomingDMGReductMult;
+        B::IncomingDEFMult = incoming_def_mult(StatType::Def, L::TargetLevel);
+        B::IncomingRESMult = res_mult(B::IncomingRESFinal);
+        B::IncomingRESFinal =
+            select(L::IncomingAttribute, |a: Attribute| StatType::AttributeRES(a));
+        B::IncomingDMGReductMult = 1 - StatType::DMGReduction;
+        // The damage before DEF and RES the character can take, counting the shield
+        B::EffectiveHP = (StatType::MaxHP + B::ShieldEffectiveHP) / B::IncomingDMGMult
+    );
+}
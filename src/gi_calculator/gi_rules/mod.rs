@@ -1,7 +1,7 @@
 use std::hash::Hash;
 
 use crate::{
-    calculator::Calculator,
+    calculator::{rules::AnonKey, Calculator},
     damage::Attribute,
     stats::{Stat, StatSheet, Type as StatType},
 };
@@ -11,6 +11,36 @@ use crate::{
 pub enum GCK {
     B(B),
     L(L),
+    /// Anonymous intermediate nodes, generated from expression rules.
+    A(usize),
+    /// Constants used by expression rules, stored as the bits of the f32.
+    C(u32),
+}
+
+impl AnonKey for GCK {
+    fn anon(id: usize) -> Self {
+        Self::A(id)
+    }
+    fn constant(val: f32) -> Self {
+        Self::C(val.to_bits())
+    }
+    fn as_constant(&self) -> Option<f32> {
+        match *self {
+            Self::C(bits) => Some(f32::from_bits(bits)),
+            _ => None,
+        }
+    }
+}
+
+impl From<B> for GCK {
+    fn from(value: B) -> Self {
+        Self::B(value)
+    }
+}
+impl From<L> for GCK {
+    fn from(value: L) -> Self {
+        Self::L(value)
+    }
 }
 
 // This is for convenience of inputting character stats.
@@ -26,11 +56,18 @@ impl From<StatType> for GCK {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum B {
     DamageInstanceOutput,
+    NonCritOutput,
+    OnCritOutput,
+    AvgCritOutput,
 
     BaseDMGFinal,
     BaseDMGPostMult,
     BaseDMGAdd,
+    AttributeBaseDMGAdd,
+    CategoryBaseDMGAdd,
     BaseDMGMult,
+    AttributeBaseDMGMult,
+    CategoryBaseDMGMult,
     BaseDMG,
     EvalScaling(S),
 
@@ -39,13 +76,15 @@ pub enum B {
     CategoryDMGBonusMult,
 
     TargetDEFMult,
+    TargetDEFRemaining,
     TotalDEFIgnore,
+    AttributeDEFIgnore,
+    CategoryDEFIgnore,
 
     TargetRESMult,
     TargetRESFinal,
     TargetBaseRES,
     TargetAttributeRES,
-    TargetAttributeRESReductNeg,
     TargetAttributeRESReduct,
 
     AmpRxnMult,
@@ -54,20 +93,84 @@ pub enum B {
     AmpRxnEMMult,
     AmpRxnBonusMult,
 
+    AddRxnDMGAdd,
+    AddRxnBaseDMG,
+    AddRxnBaseMult,
+    AddRxnTotalBonusMult,
+    AddRxnEMMult,
+    AddRxnBonusMult,
+
     CritMult,
     TotalCritRate,
+    AttributeCritRate,
+    CategoryCritRate,
     TotalCritDMG,
     AttributeCritDMG,
+    CategoryCritDMG,
+
+    TransformativeRxnOutput,
+    TransRxnBaseDMG,
+    TransRxnBaseMult,
+    TransRxnTotalBonusMult,
+    TransRxnEMMult,
+    TransRxnBonusMult,
+    TransRxnRESMult,
+    TransRxnRESFinal,
+    TransRxnCritMult,
+    TransRxnCritRate,
+    TransRxnCritDMG,
+    TransRxnAmpMult,
+
+    CharLevelMult,
+    EnemyLevelMult,
+
+    HealOutput,
+    HealBase,
+    EvalHealScaling(S),
+    HealBonusMult,
+
+    ShieldOutput,
+    ShieldBase,
+    EvalShieldScaling(S),
+    ShieldStrengthMult,
+    ShieldEffectiveHP,
+    ShieldAbsorptionMult,
+
+    CrystallizeShieldOutput,
+    CrystallizeShieldBase,
+    CrystallizeEMMult,
+    CrystallizeShieldEffectiveHP,
+    CrystallizeAbsorptionMult,
+
+    IncomingDMGOutput,
+    IncomingBaseDMG,
+    IncomingDMGMult,
+    IncomingDEFMult,
+    IncomingRESMult,
+    IncomingRESFinal,
+    IncomingDMGReductMult,
+    EffectiveHP,
 }
 
 /// Leaf Genshin Calc Keys
-/// Have no rule associated with them - the keys you will need to put values for.
+/// The inputs of the rules - the keys you will need to put values for.
+/// Some have a default rule, which a value set directly overrides until it is removed:
+/// - The total stats, `Stat(MaxHP)`, `Stat(Atk)` and `Stat(Def)`, from their base, percent and
+///   flat components.
+/// - `Reaction` and `BaseAmpRxnMult`, from `Aura` and `Attribute`.
+/// - `AbsorbedElement`, from `Aura`.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum L {
+    /// Calcindex of the `Attribute` of the hit, which is also the element it applies.
     Attribute,
     Category,
 
     Scaling(S),
+    /// Scalings off stats that have no `S`, already multiplied by their stat. Part of `BaseDMG`,
+    /// so `BaseDMGMult` applies to it like to the other scalings.
+    OtherScalingDMG,
+    /// Added to the base DMG on top of the `BaseDMGFlat` stats, for instance for buffs that are
+    /// not on the stat sheet.
     BaseDMGAdd,
 
     Stat(StatType),
@@ -81,8 +184,30 @@ pub enum L {
     // This needs to be negative!
     TargetAttributeRESReduct(Attribute),
 
+    /// Calcindex of the `Option<Aura>` of the target, left unset for no aura.
+    Aura,
     BaseAmpRxnMult,
-    AmpRxnType,
+    // Heals and shields have their own scalings and flat values, apart from those of damage.
+    HealScaling(S),
+    FlatHeal,
+    TargetIncomingHealingBonus,
+    ShieldScaling(S),
+    FlatShield,
+    /// Calcindex of the `Option<Element>` of the shield, left unset for shields without one.
+    ShieldElement,
+    /// Calcindex of the `Attribute` of the damage taken.
+    IncomingAttribute,
+    /// Scaling of the enemy attack on the enemy level multiplier.
+    IncomingScaling,
+    /// Calcindex of the `Option<Element>` absorbed from the aura by Swirl or Crystallize.
+    AbsorbedElement,
+
+    /// Calcindex of the `CritMode` of `DamageInstanceOutput`, left unset for the average.
+    CritMode,
+    /// Calcindex of an `Option<ElementalReaction>`, left unset for no reaction. Picks the
+    /// amplifying reaction of `DamageInstanceOutput`, or the transformative reaction of
+    /// `TransformativeRxnOutput`, and the additive reaction added to the base DMG.
+    Reaction,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -96,6 +221,7 @@ pub enum S {
 // Contains the actual definition of the relations between GCKs.
 pub mod gi_rules_def;
 pub use gi_rules_def::GI_RULES;
+pub mod versions;
 
 // Helpful additional methods for calculators using GCK, in other words, genshin damage calculators.
 impl Calculator<'_, GCK> {
@@ -113,3 +239,6 @@ impl Calculator<'_, GCK> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
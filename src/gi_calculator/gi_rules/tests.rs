@@ -0,0 +1,612 @@
+use std::collections::HashMap;
+
+use crate::{
+    calculator::{expr::Expr, rules::Rule, select::Select, Calculator},
+    damage::{Attribute, Category, CritMode},
+    damage_calculator::crystallize_shield_hp,
+    element::{
+        reaction::{ElementalReaction, ReactionGroup},
+        Aura, Element,
+    },
+    level_multiplier::{character_level_multiplier, enemy_level_multiplier},
+    stats::{StatSheet, Type as StatType},
+};
+
+use super::{
+    versions::{ConstantChange, EMCurve, FormulaConstants, ReactionMults, RulesVersion},
+    B, GCK, GI_RULES, L, S,
+};
+
+fn assert_round_trip<T: Select + std::fmt::Debug>() {
+    for (i, v) in T::VARIANTS.iter().enumerate() {
+        assert_eq!(v.calcindex(), i as f32);
+        assert_eq!(T::from_calcindex(i as f32), Some(*v));
+    }
+    assert_eq!(T::from_calcindex(T::VARIANTS.len() as f32), None);
+}
+
+#[test]
+fn select_round_trip() {
+    assert_round_trip::<Element>();
+    assert_round_trip::<Attribute>();
+    assert_round_trip::<Category>();
+    assert_round_trip::<CritMode>();
+    assert_round_trip::<ElementalReaction>();
+    assert_round_trip::<Option<ElementalReaction>>();
+    assert_round_trip::<ReactionGroup>();
+    assert_round_trip::<Option<Aura>>();
+    assert_round_trip::<Option<Element>>();
+    assert_eq!(Attribute::VARIANTS.len(), Element::VARIANTS.len() + 1);
+    assert_eq!(Option::<ElementalReaction>::VARIANTS[0], None);
+}
+
+#[test]
+#[should_panic(expected = "can't be selected")]
+fn unselectable_reaction() {
+    ElementalReaction::Swirl(Element::Geo).calcindex();
+}
+
+#[test]
+fn attribute_dmg_bonus_select() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.set(
+        StatType::DMGMult(Some(Attribute::Physical.into())).into(),
+        0.5,
+    );
+    calc.set(StatType::DMGMult(Some(Element::Anemo.into())).into(), 0.2);
+
+    calc.set_select(GCK::L(L::Attribute), Attribute::Physical);
+    assert_eq!(calc.get(&GCK::B(B::AttributeDMGBonusMult)), 0.5);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Anemo));
+    assert_eq!(calc.get(&GCK::B(B::AttributeDMGBonusMult)), 0.2);
+}
+
+#[test]
+fn category_dmg_bonus_select() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.set(
+        StatType::DMGMult(Some(Category::ElementalSkill.into())).into(),
+        0.3,
+    );
+    calc.set(
+        StatType::DMGMult(Some(Category::ElementalBurst.into())).into(),
+        0.4,
+    );
+
+    calc.set_select(GCK::L(L::Category), Category::ElementalSkill);
+    assert_eq!(calc.get(&GCK::B(B::CategoryDMGBonusMult)), 0.3);
+    calc.set_select(GCK::L(L::Category), Category::ElementalBurst);
+    assert_eq!(calc.get(&GCK::B(B::CategoryDMGBonusMult)), 0.4);
+}
+
+#[test]
+fn total_stats_from_components() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::BaseAtk, 1000.0),
+        (StatType::AtkPercent, 0.5),
+        (StatType::FlatAtk, 300.0),
+        (StatType::BaseHP, 10000.0),
+    ]));
+    assert_eq!(calc.get(&StatType::Atk.into()), 1800.0);
+    assert_eq!(calc.get(&StatType::MaxHP.into()), 10000.0);
+    assert_eq!(calc.get(&StatType::Def.into()), 0.0);
+
+    calc.set(StatType::FlatAtk.into(), 500.0);
+    assert_eq!(calc.get(&StatType::Atk.into()), 2000.0);
+
+    // Totals can still be set directly.
+    calc.set(StatType::Atk.into(), 4000.0);
+    assert_eq!(calc.get(&B::EvalScaling(S::Atk).into()), 0.0);
+    calc.set(L::Scaling(S::Atk).into(), 2.0);
+    assert_eq!(calc.get(&B::EvalScaling(S::Atk).into()), 8000.0);
+
+    // Components set after the total don't replace it, until the total is removed.
+    calc.set(StatType::FlatAtk.into(), 100.0);
+    calc.set(StatType::BaseAtk.into(), 2000.0);
+    assert_eq!(calc.get(&StatType::Atk.into()), 4000.0);
+    calc.remove(&StatType::Atk.into());
+    assert_eq!(calc.get(&StatType::Atk.into()), 3100.0);
+}
+
+#[test]
+fn overridden_reaction() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Pyro));
+    calc.set_select(GCK::L(L::Reaction), None::<ElementalReaction>);
+    calc.set_select(GCK::L(L::AbsorbedElement), Some(Element::Hydro));
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Cryo));
+    assert_eq!(calc.get(&B::AmpRxnMult.into()), 1.0);
+    assert_eq!(
+        calc.get(&L::AbsorbedElement.into()),
+        Some(Element::Hydro).calcindex()
+    );
+
+    calc.remove(&L::Reaction.into());
+    assert_eq!(
+        calc.get(&L::Reaction.into()),
+        Some(ElementalReaction::ForwardMelt).calcindex()
+    );
+    assert_eq!(calc.get(&L::BaseAmpRxnMult.into()), 2.0);
+}
+
+#[test]
+fn conditional_crit() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::CritRate(None), 0.6),
+        (
+            StatType::CritRate(Some(Category::ChargedAttack.into())),
+            0.5,
+        ),
+        (StatType::CritDmg(None), 1.0),
+        (StatType::CritDmg(Some(Element::Cryo.into())), 0.2),
+    ]));
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Cryo));
+    calc.set_select(GCK::L(L::Category), Category::NormalAttack);
+    assert_eq!(calc.get(&B::TotalCritRate.into()), 0.6);
+    assert_eq!(calc.get(&B::TotalCritDMG.into()), 1.2);
+    assert_eq!(calc.get(&B::CritMult.into()), 1.72);
+
+    // Crit rate above 100% does not add anything.
+    calc.set_select(GCK::L(L::Category), Category::ChargedAttack);
+    assert_eq!(calc.get(&B::TotalCritRate.into()), 1.1);
+    assert_eq!(calc.get(&B::CritMult.into()), 2.2);
+}
+
+#[test]
+fn conditional_base_dmg() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::Atk, 1000.0),
+        (
+            StatType::BaseDMGMult(Some(Category::ElementalSkill.into())),
+            0.5,
+        ),
+        (StatType::BaseDMGFlat(None), 100.0),
+        (StatType::BaseDMGFlat(Some(Element::Geo.into())), 400.0),
+    ]));
+    calc.set(L::Scaling(S::Atk).into(), 2.0);
+    calc.set(L::BaseDMGAdd.into(), 50.0);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Geo));
+    calc.set_select(GCK::L(L::Category), Category::ElementalSkill);
+    assert_eq!(calc.get(&B::BaseDMGFinal.into()), 3550.0);
+
+    calc.set_select(GCK::L(L::Attribute), Attribute::Physical);
+    calc.set_select(GCK::L(L::Category), Category::NormalAttack);
+    assert_eq!(calc.get(&B::BaseDMGFinal.into()), 2150.0);
+}
+
+#[test]
+fn def_ignore_and_shred() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::Level, 90.0),
+        (StatType::DefIgnore(None), 0.2),
+        (
+            StatType::DefIgnore(Some(Category::ElementalBurst.into())),
+            0.4,
+        ),
+    ]));
+    calc.set(L::TargetLevel.into(), 90.0);
+    calc.set_select(GCK::L(L::Category), Category::ElementalBurst);
+    assert!((calc.get(&B::TotalDEFIgnore.into()) - 0.6).abs() < 1e-6);
+
+    calc.set(L::TargetDEFReduct.into(), 0.5);
+    assert!((calc.get(&B::TargetDEFRemaining.into()) - 0.2).abs() < 1e-6);
+    assert!((calc.get(&B::TargetDEFMult.into()) - 1.0 / 1.2).abs() < 1e-6);
+
+    // The target's DEF can't go below 0.
+    calc.set(L::TargetDEFReduct.into(), 1.5);
+    assert_eq!(calc.get(&B::TargetDEFRemaining.into()), 0.0);
+    assert_eq!(calc.get(&B::TargetDEFMult.into()), 1.0);
+}
+
+#[test]
+fn transformative_reaction() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::ElementalMastery, 2000.0),
+        (StatType::RxnDMGMult(ElementalReaction::Overloaded), 0.4),
+        (StatType::DefIgnore(None), 0.5),
+    ]));
+    calc.set(B::CharLevelMult.into(), 1000.0);
+    calc.set(L::TargetAttributeRES(Element::Pyro.into()).into(), 0.1);
+    calc.set(L::TargetAttributeRES(Element::Cryo.into()).into(), 0.5);
+
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Overloaded));
+    // 2.75 * 1000 * (1 + 8 + 0.4) * 0.9
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 23265.0).abs() < 0.1);
+
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Superconduct));
+    // 1.5 * 1000 * (1 + 8) * 0.5
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 6750.0).abs() < 0.1);
+
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::ForwardMelt));
+    assert_eq!(calc.get(&B::TransformativeRxnOutput.into()), 0.0);
+}
+
+#[test]
+fn reaction_group_bonus() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (
+            StatType::RxnDMGMult(ElementalReaction::ReverseVaporize),
+            0.15,
+        ),
+        (StatType::RxnGroupDMGMult(ReactionGroup::Vaporize), 0.2),
+        (StatType::RxnGroupDMGMult(ReactionGroup::Amplifying), 0.05),
+        (
+            StatType::RxnGroupDMGMult(ReactionGroup::Transformative),
+            0.4,
+        ),
+    ]));
+
+    calc.set_select(
+        GCK::L(L::Reaction),
+        Some(ElementalReaction::ReverseVaporize),
+    );
+    assert!((calc.get(&B::AmpRxnBonusMult.into()) - 0.4).abs() < 1e-6);
+    calc.set_select(
+        GCK::L(L::Reaction),
+        Some(ElementalReaction::ForwardVaporize),
+    );
+    assert!((calc.get(&B::AmpRxnBonusMult.into()) - 0.25).abs() < 1e-6);
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::ForwardMelt));
+    assert!((calc.get(&B::AmpRxnBonusMult.into()) - 0.05).abs() < 1e-6);
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Bloom));
+    assert_eq!(calc.get(&B::AmpRxnBonusMult.into()), 0.0);
+    assert!((calc.get(&B::TransRxnBonusMult.into()) - 0.4).abs() < 1e-6);
+}
+
+#[test]
+fn transformative_reaction_crit() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::RxnCritRate(ElementalReaction::Bloom), 0.2),
+        (StatType::RxnCritDmg(ElementalReaction::Bloom), 1.0),
+    ]));
+    calc.set(B::TransRxnBaseDMG.into(), 1000.0);
+    calc.set(B::TransRxnRESMult.into(), 1.0);
+
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Bloom));
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 1200.0).abs() < 0.1);
+    calc.set_select(GCK::L(L::CritMode), CritMode::OnCrit);
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 2000.0).abs() < 0.1);
+
+    // Other reactions don't crit.
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Hyperbloom));
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 1000.0).abs() < 0.1);
+}
+
+#[test]
+fn level_multipliers() {
+    assert_eq!(character_level_multiplier(1.0), 17.165605);
+    assert_eq!(character_level_multiplier(80.0), 1077.4437);
+    assert_eq!(character_level_multiplier(90.0), 1446.8535);
+    assert_eq!(character_level_multiplier(100.0), 2030.0718);
+    assert_eq!(character_level_multiplier(90.5), 1446.8535);
+    assert_eq!(character_level_multiplier(120.0), 2030.0718);
+    assert_eq!(enemy_level_multiplier(90.0), 1446.8535);
+    assert_eq!(enemy_level_multiplier(100.0), 1674.8092);
+
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.set(StatType::Level.into(), 90.0);
+    calc.set(L::TargetLevel.into(), 100.0);
+    assert_eq!(calc.get(&B::CharLevelMult.into()), 1446.8535);
+    assert_eq!(calc.get(&B::EnemyLevelMult.into()), 1674.8092);
+}
+
+#[test]
+fn additive_reaction() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::Level, 90.0),
+        (StatType::Atk, 2000.0),
+        (StatType::ElementalMastery, 1200.0),
+        (StatType::RxnDMGMult(ElementalReaction::Spread), 0.5),
+    ]));
+    calc.set(L::Scaling(S::Atk).into(), 1.0);
+    assert_eq!(calc.get(&B::BaseDMGFinal.into()), 2000.0);
+
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Spread));
+    // 1.25 * 1446.8535 * (1 + 2.5 + 0.5)
+    let spread = 1.25 * 1446.8535 * 4.0;
+    assert!((calc.get(&B::BaseDMGFinal.into()) - (2000.0 + spread)).abs() < 0.1);
+
+    calc.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Aggravate));
+    let aggravate = 1.15 * 1446.8535 * 3.5;
+    assert!((calc.get(&B::BaseDMGFinal.into()) - (2000.0 + aggravate)).abs() < 0.1);
+}
+
+#[test]
+fn reaction_pairs() {
+    use ElementalReaction as R;
+    let pairs = [
+        (Aura::Electro, Element::Dendro, R::Quicken),
+        (Aura::Dendro, Element::Electro, R::Quicken),
+        (Aura::Cryo, Element::Pyro, R::ForwardMelt),
+        (Aura::Frozen, Element::Pyro, R::ForwardMelt),
+        (
+            Aura::ElectroCharged,
+            Element::Anemo,
+            R::Swirl(Element::Hydro),
+        ),
+        (
+            Aura::ElectroCharged,
+            Element::Geo,
+            R::Crystallize(Element::Hydro),
+        ),
+    ];
+    for (aura, trigger, reaction) in pairs {
+        assert_eq!(
+            ElementalReaction::from_elements(aura, trigger),
+            Some(reaction),
+            "{aura:?} {trigger:?}"
+        );
+    }
+}
+
+#[test]
+fn reaction_from_aura() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::RxnDMGMult(ElementalReaction::ForwardMelt), 0.15),
+        (StatType::RxnDMGMult(ElementalReaction::ReverseMelt), 0.25),
+    ]));
+    assert_eq!(calc.get(&L::Reaction.into()), 0.0);
+
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Cryo));
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Pyro));
+    let reaction = calc.get(&L::Reaction.into());
+    assert_eq!(
+        Option::<ElementalReaction>::from_calcindex(reaction),
+        Some(Some(ElementalReaction::ForwardMelt))
+    );
+    assert_eq!(calc.get(&L::BaseAmpRxnMult.into()), 2.0);
+    assert_eq!(calc.get(&B::AmpRxnMult.into()), 2.0 * 1.15);
+
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Pyro));
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Cryo));
+    assert_eq!(calc.get(&L::BaseAmpRxnMult.into()), 1.5);
+    assert_eq!(calc.get(&B::AmpRxnMult.into()), 1.5 * 1.25);
+
+    calc.set_select(GCK::L(L::Attribute), Attribute::Physical);
+    assert_eq!(calc.get(&B::AmpRxnMult.into()), 1.0);
+
+    // Electro on Pyro is routed to the transformative reaction instead.
+    calc.set(B::CharLevelMult.into(), 1000.0);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Electro));
+    assert_eq!(calc.get(&B::AmpRxnMult.into()), 1.0);
+    assert_eq!(calc.get(&B::TransRxnBaseDMG.into()), 2750.0);
+}
+
+#[test]
+fn crit_modes() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::Atk, 1000.0),
+        (StatType::CritRate(None), 0.5),
+        (StatType::CritDmg(None), 1.0),
+    ]));
+    calc.set(L::Scaling(S::Atk).into(), 1.0);
+    calc.set(B::TargetDEFMult.into(), 0.5);
+    calc.set(B::TargetRESMult.into(), 1.0);
+
+    assert_eq!(calc.get(&B::NonCritOutput.into()), 500.0);
+    assert_eq!(calc.get(&B::OnCritOutput.into()), 1000.0);
+    assert_eq!(calc.get(&B::AvgCritOutput.into()), 750.0);
+
+    assert_eq!(calc.get(&B::DamageInstanceOutput.into()), 750.0);
+    calc.set_select(GCK::L(L::CritMode), CritMode::NonCrit);
+    assert_eq!(calc.get(&B::DamageInstanceOutput.into()), 500.0);
+    calc.set_select(GCK::L(L::CritMode), CritMode::OnCrit);
+    assert_eq!(calc.get(&B::DamageInstanceOutput.into()), 1000.0);
+}
+
+#[test]
+fn heal_and_shield() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::MaxHP, 30000.0),
+        (StatType::HealingBonus, 0.2),
+        (StatType::ShieldStrength, 0.25),
+    ]));
+    calc.set(L::HealScaling(S::MaxHP).into(), 0.1);
+    calc.set(L::ShieldScaling(S::MaxHP).into(), 0.1);
+    calc.set(L::FlatHeal.into(), 1000.0);
+    calc.set(L::FlatShield.into(), 2000.0);
+    calc.set(L::TargetIncomingHealingBonus.into(), 0.3);
+    assert_eq!(calc.get(&B::HealOutput.into()), 6000.0);
+    assert_eq!(calc.get(&B::ShieldOutput.into()), 6250.0);
+    assert_eq!(calc.get(&B::ShieldEffectiveHP.into()), 6250.0);
+
+    calc.set_select(GCK::L(L::ShieldElement), Some(Element::Cryo));
+    calc.set_select(GCK::L(L::IncomingAttribute), Attribute::from(Element::Cryo));
+    assert_eq!(calc.get(&B::ShieldEffectiveHP.into()), 15625.0);
+    calc.set_select(GCK::L(L::IncomingAttribute), Attribute::Physical);
+    assert_eq!(calc.get(&B::ShieldEffectiveHP.into()), 6250.0);
+
+    calc.set_select(GCK::L(L::ShieldElement), Some(Element::Geo));
+    assert_eq!(calc.get(&B::ShieldEffectiveHP.into()), 9375.0);
+
+    // The damage scalings of the talent don't count.
+    calc.set(L::Scaling(S::MaxHP).into(), 0.5);
+    assert_eq!(calc.get(&B::HealOutput.into()), 6000.0);
+    assert_eq!(calc.get(&B::ShieldOutput.into()), 6250.0);
+}
+
+#[test]
+fn crystallize_shield() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::Level, 90.0),
+        (StatType::ElementalMastery, 1400.0),
+        (StatType::ShieldStrength, 0.2),
+    ]));
+    // 1851.06 * (1 + 2.22) * 1.2
+    let shield = 1851.06 * 3.22 * 1.2;
+    assert!((calc.get(&B::CrystallizeShieldOutput.into()) - shield).abs() < 1.0);
+    assert!((crystallize_shield_hp(90.0, 1400.0).unwrap() * 1.2 - shield).abs() < 1.0);
+    assert!((crystallize_shield_hp(1.0, 0.0).unwrap() - 91.1791).abs() < 1e-3);
+    // Levels without data give no shield rather than a guess.
+    assert_eq!(crystallize_shield_hp(50.0, 0.0), None);
+    calc.set(StatType::Level.into(), 50.0);
+    assert!(calc.get(&B::CrystallizeShieldOutput.into()).is_nan());
+    calc.set(StatType::Level.into(), 90.0);
+
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Frozen));
+    calc.set_select(GCK::L(L::IncomingAttribute), Attribute::from(Element::Cryo));
+    let effective = calc.get(&B::CrystallizeShieldEffectiveHP.into());
+    assert!((effective - shield * 2.5).abs() < 1.0);
+    calc.set_select(GCK::L(L::IncomingAttribute), Attribute::from(Element::Pyro));
+    let effective = calc.get(&B::CrystallizeShieldEffectiveHP.into());
+    assert!((effective - shield).abs() < 1.0);
+}
+
+#[test]
+fn swirl_absorbed_element() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([(
+        StatType::RxnDMGMult(ElementalReaction::Swirl(Element::Pyro)),
+        0.6,
+    )]));
+    calc.set(B::CharLevelMult.into(), 1000.0);
+    calc.set(L::TargetAttributeRES(Element::Anemo.into()).into(), 0.7);
+    calc.set(L::TargetAttributeRES(Element::Pyro.into()).into(), 0.1);
+    calc.set(
+        L::TargetAttributeRESReduct(Element::Pyro.into()).into(),
+        0.4,
+    );
+    calc.set(L::TargetAttributeRES(Element::Hydro.into()).into(), 0.1);
+
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Anemo));
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Pyro));
+    assert_eq!(
+        Option::<ElementalReaction>::from_calcindex(calc.get(&L::Reaction.into())),
+        Some(Some(ElementalReaction::Swirl(Element::Pyro)))
+    );
+    // 0.6 * 1000 * (1 + 0.6) * (1 - (0.1 - 0.4) / 2)
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 1104.0).abs() < 0.01);
+
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Hydro));
+    // 0.6 * 1000 * 1 * (1 - 0.1)
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 540.0).abs() < 0.01);
+
+    calc.set_select(GCK::L(L::Aura), Some(Aura::ElectroCharged));
+    assert!((calc.get(&B::TransformativeRxnOutput.into()) - 540.0).abs() < 0.01);
+
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Dendro));
+    assert_eq!(calc.get(&B::TransformativeRxnOutput.into()), 0.0);
+}
+
+#[test]
+fn incoming_damage() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::MaxHP, 20000.0),
+        (StatType::Def, 1000.0),
+        (StatType::AttributeRES(Element::Hydro.into()), 0.2),
+        (StatType::DMGReduction, 0.2),
+    ]));
+    calc.set(L::TargetLevel.into(), 100.0);
+    calc.set(L::IncomingScaling.into(), 2.0);
+    calc.set_select(
+        GCK::L(L::IncomingAttribute),
+        Attribute::from(Element::Hydro),
+    );
+    // (500 + 500) / (1000 + 500 + 500) * (1 - 0.2) * (1 - 0.2)
+    let mult = 0.5 * 0.8 * 0.8;
+    assert!((calc.get(&B::IncomingDMGMult.into()) - mult).abs() < 1e-6);
+    let incoming = 2.0 * 1674.8092 * mult;
+    assert!((calc.get(&B::IncomingDMGOutput.into()) - incoming).abs() < 0.01);
+    assert!((calc.get(&B::EffectiveHP.into()) - 20000.0 / mult).abs() < 0.1);
+
+    // A Hydro shield absorbs 250% of Hydro damage.
+    calc.set(L::FlatShield.into(), 4000.0);
+    calc.set_select(GCK::L(L::ShieldElement), Some(Element::Hydro));
+    assert!((calc.get(&B::EffectiveHP.into()) - 30000.0 / mult).abs() < 0.1);
+    // Damage scalings don't make a shield.
+    calc.set(L::Scaling(S::MaxHP).into(), 1.0);
+    assert!((calc.get(&B::EffectiveHP.into()) - 30000.0 / mult).abs() < 0.1);
+
+    calc.set_select(GCK::L(L::IncomingAttribute), Attribute::Physical);
+    let mult = 0.5 * 0.8;
+    assert!((calc.get(&B::EffectiveHP.into()) - 24000.0 / mult).abs() < 0.1);
+}
+
+/// Overrides the amplifying EM bonus with a flat 50%.
+fn flat_amp_em(_: &FormulaConstants, rules: &mut HashMap<GCK, Rule<GCK>>, anon: &mut usize) {
+    Expr::constant(0.5).lower(B::AmpRxnEMMult.into(), anon, rules);
+}
+
+#[test]
+fn rule_versions() {
+    assert_eq!(RulesVersion::latest().name, "5.x");
+    assert!(RulesVersion::get("1.0").is_none());
+    assert!(std::ptr::eq(*GI_RULES, RulesVersion::latest().rules()));
+    for name in ["4.x", "5.x"] {
+        let version = RulesVersion::get(name).unwrap();
+        let mut calc = version.calculator();
+        calc.set(StatType::ElementalMastery.into(), 1400.0);
+        assert!((calc.get(&B::AmpRxnEMMult.into()) - 1.39).abs() < 1e-6);
+        calc.set(StatType::Level.into(), 90.0);
+        calc.set(L::TargetLevel.into(), 100.0);
+        assert_eq!(calc.get(&B::CharLevelMult.into()), 1446.8535);
+        assert_eq!(calc.get(&B::EnemyLevelMult.into()), 1674.8092);
+        calc.set_select(
+            L::Reaction.into(),
+            Some(ElementalReaction::Swirl(Element::Pyro)),
+        );
+        assert_eq!(calc.get(&B::TransRxnBaseMult.into()), 0.6);
+    }
+    let old = RulesVersion::get("4.x").unwrap();
+    assert!(old.diff(RulesVersion::get("5.x").unwrap()).is_empty());
+
+    let mut char_levels = *old.constants.char_level_mults;
+    char_levels[89] = 1500.0;
+    let new = RulesVersion {
+        name: "test",
+        constants: FormulaConstants {
+            transformative_em: EMCurve {
+                mult: 20.0,
+                offset: 2000.0,
+            },
+            reaction_mults: ReactionMults {
+                swirl: 0.7,
+                ..old.constants.reaction_mults
+            },
+            char_level_mults: Box::leak(Box::new(char_levels)),
+            ..old.constants
+        },
+        overrides: Some(flat_amp_em),
+    };
+    let rules = new.build();
+    let mut calc = Calculator::new(&rules);
+    calc.set(StatType::ElementalMastery.into(), 2000.0);
+    assert_eq!(calc.get(&B::AmpRxnEMMult.into()), 0.5);
+    assert_eq!(calc.get(&B::TransRxnEMMult.into()), 10.0);
+    calc.set(StatType::Level.into(), 90.0);
+    assert_eq!(calc.get(&B::CharLevelMult.into()), 1500.0);
+    calc.set_select(
+        L::Reaction.into(),
+        Some(ElementalReaction::Swirl(Element::Pyro)),
+    );
+    assert_eq!(calc.get(&B::TransRxnBaseMult.into()), 0.7);
+
+    let diff = old.diff(&new);
+    let change = |name: &str, from, to| ConstantChange {
+        name: name.to_string(),
+        from,
+        to,
+    };
+    assert_eq!(
+        diff.constants,
+        [
+            change("transformative_em.mult", 16.0, 20.0),
+            change("reaction_mults.swirl", 0.6, 0.7),
+            change("char_level_mults.90", 1446.8535, 1500.0),
+        ]
+    );
+    assert_eq!(diff.rules, [GCK::from(B::AmpRxnEMMult)]);
+}
@@ -0,0 +1,295 @@
+//! The genshin rule set as of different game versions. Each version has its own formula constants,
+//! and can replace some of the shared rules when a patch changes how they work.
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::{
+    calculator::{
+        expr::Expr,
+        rules::{Rule, Rules},
+        Calculator,
+    },
+    element::reaction::ElementalReaction,
+    key_names::KeyName,
+    level_multiplier::{CHARACTER_LEVEL_MULTIPLIERS, ENEMY_LEVEL_MULTIPLIERS},
+    stats::Type as StatType,
+};
+
+use super::{gi_rules_def::base_rules, GCK};
+
+/// A bonus of `mult * EM / (EM + offset)` from Elemental Mastery.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EMCurve {
+    pub mult: f32,
+    pub offset: f32,
+}
+impl EMCurve {
+    pub fn eval(&self, em: f32) -> f32 {
+        self.mult * em / (em + self.offset)
+    }
+
+    pub fn expr(&self) -> Expr<GCK> {
+        let em = || Expr::key(StatType::ElementalMastery);
+        Expr::constant(self.mult) * em() / (em() + Expr::constant(self.offset))
+    }
+}
+
+/// The base multipliers of reactions. Amplifying reactions multiply the damage of the hit that
+/// triggered them, and the other reactions the level multiplier of the character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReactionMults {
+    pub forward_amp: f32,
+    pub reverse_amp: f32,
+    pub aggravate: f32,
+    pub spread: f32,
+    pub swirl: f32,
+    pub electro_charged: f32,
+    pub overloaded: f32,
+    pub superconduct: f32,
+    pub bloom: f32,
+    pub hyperbloom: f32,
+    pub burgeon: f32,
+    pub burning: f32,
+    pub shatter: f32,
+}
+impl ReactionMults {
+    /// The base multiplier of the reaction, or `None` for reactions that deal no damage.
+    pub fn get(&self, reaction: ElementalReaction) -> Option<f32> {
+        use ElementalReaction as R;
+        match reaction {
+            R::ForwardVaporize | R::ForwardMelt => Some(self.forward_amp),
+            R::ReverseVaporize | R::ReverseMelt => Some(self.reverse_amp),
+            R::Aggravate => Some(self.aggravate),
+            R::Spread => Some(self.spread),
+            R::Swirl(_) => Some(self.swirl),
+            R::ElectroCharged => Some(self.electro_charged),
+            R::Overloaded => Some(self.overloaded),
+            R::Superconduct => Some(self.superconduct),
+            R::Bloom => Some(self.bloom),
+            R::Hyperbloom => Some(self.hyperbloom),
+            R::Burgeon => Some(self.burgeon),
+            R::Burning => Some(self.burning),
+            R::Shatter => Some(self.shatter),
+            R::Crystallize(_) | R::Quicken | R::Freeze => None,
+        }
+    }
+
+    fn entries(&self) -> [(&'static str, f32); 13] {
+        [
+            ("forward_amp", self.forward_amp),
+            ("reverse_amp", self.reverse_amp),
+            ("aggravate", self.aggravate),
+            ("spread", self.spread),
+            ("swirl", self.swirl),
+            ("electro_charged", self.electro_charged),
+            ("overloaded", self.overloaded),
+            ("superconduct", self.superconduct),
+            ("bloom", self.bloom),
+            ("hyperbloom", self.hyperbloom),
+            ("burgeon", self.burgeon),
+            ("burning", self.burning),
+            ("shatter", self.shatter),
+        ]
+    }
+}
+
+/// The numbers in the formulas which patches may change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormulaConstants {
+    pub amp_em: EMCurve,
+    pub additive_em: EMCurve,
+    pub transformative_em: EMCurve,
+    pub crystallize_em: EMCurve,
+    pub reaction_mults: ReactionMults,
+    /// Level multipliers of characters and enemies, for levels 1 to 100.
+    pub char_level_mults: &'static [f32; 100],
+    pub enemy_level_mults: &'static [f32; 100],
+}
+impl FormulaConstants {
+    /// Every constant along with its name, in a fixed order. Level multipliers are named by their
+    /// level, for instance `char_level_mults.90`.
+    pub fn entries(&self) -> Vec<(String, f32)> {
+        let curves = [
+            ("amp_em", self.amp_em),
+            ("additive_em", self.additive_em),
+            ("transformative_em", self.transformative_em),
+            ("crystallize_em", self.crystallize_em),
+        ];
+        let tables = [
+            ("char_level_mults", self.char_level_mults),
+            ("enemy_level_mults", self.enemy_level_mults),
+        ];
+        let curves = curves.into_iter().flat_map(|(name, curve)| {
+            [
+                (format!("{name}.mult"), curve.mult),
+                (format!("{name}.offset"), curve.offset),
+            ]
+        });
+        let reactions = self
+            .reaction_mults
+            .entries()
+            .into_iter()
+            .map(|(name, val)| (format!("reaction_mults.{name}"), val));
+        let tables = tables.into_iter().flat_map(|(name, table)| {
+            (1..)
+                .zip(table)
+                .map(move |(level, &val)| (format!("{name}.{level}"), val))
+        });
+        curves.chain(reactions).chain(tables).collect()
+    }
+}
+
+/// Adds rules on top of the base rules of a version, replacing the base rules with the same
+/// targets. Gets the next free id for anonymous nodes, like [`Expr::lower`].
+pub type RuleOverrides = fn(&FormulaConstants, &mut HashMap<GCK, Rule<GCK>>, &mut usize);
+
+pub struct RulesVersion {
+    pub name: &'static str,
+    pub constants: FormulaConstants,
+    pub overrides: Option<RuleOverrides>,
+}
+
+const CONSTANTS_4X: FormulaConstants = FormulaConstants {
+    // 2.78 as in the damage formula of the KQM Theorycrafting Library and the Genshin Impact wiki.
+    // Some calculators use 25/9, about 2.778, which changes a melt or vaporize by less than 0.1%.
+    // Neither has been checked against hits recorded in game yet.
+    amp_em: EMCurve {
+        mult: 2.78,
+        offset: 1400.0,
+    },
+    additive_em: EMCurve {
+        mult: 5.0,
+        offset: 1200.0,
+    },
+    transformative_em: EMCurve {
+        mult: 16.0,
+        offset: 2000.0,
+    },
+    crystallize_em: EMCurve {
+        mult: 4.44,
+        offset: 1400.0,
+    },
+    reaction_mults: ReactionMults {
+        forward_amp: 2.0,
+        reverse_amp: 1.5,
+        aggravate: 1.15,
+        spread: 1.25,
+        swirl: 0.6,
+        electro_charged: 2.0,
+        overloaded: 2.75,
+        superconduct: 1.5,
+        bloom: 2.0,
+        hyperbloom: 3.0,
+        burgeon: 3.0,
+        burning: 0.25,
+        shatter: 3.0,
+    },
+    char_level_mults: &CHARACTER_LEVEL_MULTIPLIERS,
+    enemy_level_mults: &ENEMY_LEVEL_MULTIPLIERS,
+};
+
+/// None of the numbers modelled here changed in 5.x.
+const CONSTANTS_5X: FormulaConstants = CONSTANTS_4X;
+
+/// Every known version, oldest first.
+pub static VERSIONS: [RulesVersion; 2] = [
+    RulesVersion {
+        name: "4.x",
+        constants: CONSTANTS_4X,
+        overrides: None,
+    },
+    RulesVersion {
+        name: "5.x",
+        constants: CONSTANTS_5X,
+        overrides: None,
+    },
+];
+
+static VERSION_RULES: LazyLock<HashMap<&'static str, Rules<GCK>>> =
+    LazyLock::new(|| VERSIONS.iter().map(|v| (v.name, v.build())).collect());
+
+impl RulesVersion {
+    pub fn get(name: &str) -> Option<&'static Self> {
+        VERSIONS.iter().find(|v| v.name == name)
+    }
+
+    pub fn latest() -> &'static Self {
+        VERSIONS.last().expect("there is at least one version")
+    }
+
+    /// Builds the rule set of the version. Prefer [`RulesVersion::rules`] for the known versions,
+    /// which only builds them once.
+    pub fn build(&self) -> Rules<GCK> {
+        let mut rules = HashMap::new();
+        let mut anon = 0;
+        base_rules(&self.constants, &mut rules, &mut anon);
+        if let Some(overrides) = self.overrides {
+            overrides(&self.constants, &mut rules, &mut anon);
+        }
+        Rules::new(rules)
+    }
+
+    pub fn rules(&self) -> &'static Rules<GCK> {
+        VERSION_RULES
+            .get(self.name)
+            .expect("only versions of VERSIONS can be looked up")
+    }
+
+    pub fn calculator(&self) -> Calculator<'static, GCK> {
+        Calculator::new(self.rules())
+    }
+
+    /// The named keys that the overrides of this version define rules for, sorted by name.
+    pub fn overridden_keys(&self) -> Vec<GCK> {
+        let mut rules = HashMap::new();
+        if let Some(overrides) = self.overrides {
+            overrides(&self.constants, &mut rules, &mut 0);
+        }
+        let mut keys: Vec<_> = rules
+            .into_keys()
+            .filter(|k| !matches!(k, GCK::A(_) | GCK::C(_)))
+            .collect();
+        keys.sort_by_cached_key(KeyName::name);
+        keys
+    }
+
+    /// What changed going from this version to the other one.
+    pub fn diff(&self, other: &RulesVersion) -> VersionDiff {
+        let constants = self
+            .constants
+            .entries()
+            .into_iter()
+            .zip(other.constants.entries())
+            .filter(|((_, from), (_, to))| from != to)
+            .map(|((name, from), (_, to))| ConstantChange { name, from, to })
+            .collect();
+        let mut rules = self.overridden_keys();
+        for key in other.overridden_keys() {
+            if !rules.contains(&key) {
+                rules.push(key);
+            }
+        }
+        rules.sort_by_cached_key(KeyName::name);
+        VersionDiff { constants, rules }
+    }
+}
+
+/// The differences between two versions. Rules are listed when either version overrides them, as
+/// overrides can't be compared.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VersionDiff {
+    pub constants: Vec<ConstantChange>,
+    pub rules: Vec<GCK>,
+}
+impl VersionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty() && self.rules.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstantChange {
+    pub name: String,
+    pub from: f32,
+    pub to: f32,
+}
@@ -1 +1,2 @@
 pub mod gi_rules;
+pub mod multi_target;
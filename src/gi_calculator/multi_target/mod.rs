@@ -0,0 +1,127 @@
+//! Evaluating one hit against several targets, for instance an AoE talent hitting a pack of
+//! enemies with different levels, RES and auras.
+
+use crate::{
+    calculator::{select::Select, Calculator},
+    damage::Attribute,
+    element::{reaction::ElementalReaction, Aura},
+    enemy::Enemy,
+    gi_calculator::gi_rules::{B, GCK, L},
+};
+
+/// One of the targets of a multi-target evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub enemy: Enemy,
+    pub aura: Option<Aura>,
+}
+impl Target {
+    pub fn new(enemy: Enemy) -> Self {
+        Self { enemy, aura: None }
+    }
+
+    pub fn with_aura(mut self, aura: Aura) -> Self {
+        self.aura = Some(aura);
+        self
+    }
+
+    /// Sets the target leaves of the calculator, including the aura.
+    pub fn write_to(&self, calc: &mut Calculator<GCK>) {
+        self.enemy.write_to(calc);
+        calc.set_select(GCK::L(L::Aura), self.aura);
+    }
+
+    /// The keys `write_to` sets or removes.
+    fn keys() -> impl Iterator<Item = GCK> {
+        [
+            GCK::L(L::TargetLevel),
+            GCK::B(B::TargetDEFMult),
+            GCK::L(L::Aura),
+        ]
+        .into_iter()
+        .chain(
+            Attribute::VARIANTS
+                .iter()
+                .map(|&a| GCK::L(L::TargetAttributeRES(a))),
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MultiTargetOutput {
+    /// The index of each target hit, with the value of the output against it.
+    pub per_target: Vec<(usize, f32)>,
+    pub total: f32,
+}
+
+impl Calculator<'_, GCK> {
+    /// Evaluates the output against every target whose index is in `hit`, or returns `None` if
+    /// one of them is out of bounds.
+    ///
+    /// Only the target leaves change between targets, so the values that don't depend on the
+    /// target, like `BaseDMGFinal` or `CritMult`, are calculated once and reused from the cache.
+    /// The target leaves of the calculator are restored afterwards.
+    pub fn evaluate_targets(
+        &mut self,
+        output: &GCK,
+        targets: &[Target],
+        hit: &[usize],
+    ) -> Option<MultiTargetOutput> {
+        if hit.iter().any(|&i| i >= targets.len()) {
+            return None;
+        }
+        let saved = self.save(Target::keys());
+        let mut result = MultiTargetOutput::default();
+        for &i in hit {
+            targets[i].write_to(self);
+            let val = self.get(output);
+            result.per_target.push((i, val));
+            result.total += val;
+        }
+        self.reset(saved);
+        Some(result)
+    }
+
+    /// Evaluates the Swirl triggered on the target at index `trigger`, which deals damage of the
+    /// element absorbed from its aura to every target in `hit`, usually including `trigger`.
+    ///
+    /// The Swirl damage on each target uses the RES of that target to the absorbed element, and is
+    /// amplified by the aura of the target when it reacts with that element. Returns `None` when
+    /// an index is out of bounds or the aura of the trigger can't be swirled. The reaction is
+    /// restored afterwards.
+    pub fn evaluate_swirl(
+        &mut self,
+        targets: &[Target],
+        trigger: usize,
+        hit: &[usize],
+    ) -> Option<MultiTargetOutput> {
+        let element = targets.get(trigger)?.aura?.absorbed_element()?;
+        let saved = self.save([GCK::L(L::Reaction)]);
+        self.set_select(GCK::L(L::Reaction), Some(ElementalReaction::Swirl(element)));
+        let result = self.evaluate_targets(&B::TransformativeRxnOutput.into(), targets, hit);
+        self.reset(saved);
+        result
+    }
+
+    /// The values given for the keys, `None` for those that weren't.
+    fn save(&self, keys: impl IntoIterator<Item = GCK>) -> Vec<(GCK, Option<f32>)> {
+        keys.into_iter()
+            .map(|k| (k.clone(), self.given(&k)))
+            .collect()
+    }
+
+    /// Sets the keys back to values returned by `save`.
+    fn reset(&mut self, saved: Vec<(GCK, Option<f32>)>) {
+        for (key, val) in saved {
+            match val {
+                Some(val) => self.set(key, val),
+                None => {
+                    self.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,166 @@
+use crate::{
+    calculator::{select::Select, Calculator},
+    damage::Attribute,
+    element::{reaction::ElementalReaction, Aura, Element},
+    enemy::Enemy,
+    stats::{StatSheet, Type as StatType},
+    B, GCK, GI_RULES, L, S,
+};
+
+use super::Target;
+
+fn attacker() -> Calculator<'static, GCK> {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.import_stat_sheet(&StatSheet::from([
+        (StatType::Level, 90.0),
+        (StatType::Atk, 1000.0),
+    ]));
+    calc.set(L::Scaling(S::Atk).into(), 1.0);
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Cryo));
+    calc
+}
+
+#[test]
+fn per_target_and_total() {
+    let targets = [
+        Target::new(Enemy::new(90.0)),
+        Target::new(Enemy::new(90.0).with_res(Element::Cryo.into(), 0.5)),
+        Target::new(Enemy::preset("cryo_slime").unwrap()),
+        Target::new(Enemy::new(90.0).with_res(Element::Cryo.into(), -0.2)),
+    ];
+    let mut calc = attacker();
+    let output = calc
+        .evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[0, 1, 2])
+        .unwrap();
+    assert_eq!(output.per_target.len(), 3);
+    assert_eq!(output.per_target[0], (0, 450.0));
+    assert_eq!(output.per_target[1], (1, 250.0));
+    assert_eq!(output.per_target[2], (2, 0.0));
+    assert_eq!(output.total, 700.0);
+
+    let output = calc
+        .evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[3])
+        .unwrap();
+    assert_eq!(output.per_target, vec![(3, 550.0)]);
+}
+
+#[test]
+fn attacker_values_shared() {
+    let targets = [
+        Target::new(Enemy::new(90.0)),
+        Target::new(Enemy::new(90.0).with_res(Element::Cryo.into(), 0.5)),
+    ];
+    let mut calc = attacker();
+    // Overriding an attacker side value shows that it is not recalculated for each target.
+    calc.set(B::CritMult.into(), 2.0);
+    let output = calc
+        .evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[0, 1])
+        .unwrap();
+    assert_eq!(output.total, 900.0 + 500.0);
+}
+
+#[test]
+fn reactions_per_target() {
+    let targets = [
+        Target::new(Enemy::new(90.0)),
+        Target::new(Enemy::new(90.0)).with_aura(Aura::Pyro),
+    ];
+    let mut calc = attacker();
+    let output = calc
+        .evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[0, 1])
+        .unwrap();
+    // Reverse melt on the second target only.
+    assert_eq!(output.per_target, vec![(0, 450.0), (1, 675.0)]);
+}
+
+#[test]
+fn swirl_on_several_targets() {
+    let targets = [
+        Target::new(Enemy::new(90.0)).with_aura(Aura::Pyro),
+        Target::new(Enemy::new(90.0)).with_aura(Aura::Cryo),
+        Target::new(Enemy::new(90.0).with_res(Element::Pyro.into(), 0.5)),
+        Target::new(Enemy::new(90.0)).with_aura(Aura::Hydro),
+        Target::new(Enemy::new(90.0)).with_aura(Aura::Dendro),
+    ];
+    let mut calc = attacker();
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Anemo));
+    assert_eq!(calc.evaluate_swirl(&targets, 2, &[0, 1]), None);
+    assert_eq!(calc.evaluate_swirl(&targets, 4, &[0, 1]), None);
+
+    // 0.6 * 1446.8535, with 10% Pyro RES unless set otherwise.
+    let base = 868.1121;
+    let output = calc.evaluate_swirl(&targets, 0, &[0, 1, 2, 3]).unwrap();
+    let expected = [
+        base * 0.9,
+        // Pyro on Cryo is a forward melt, and Pyro on Hydro a reverse vaporize.
+        base * 0.9 * 2.0,
+        base * 0.5,
+        base * 0.9 * 1.5,
+    ];
+    for (&(i, val), expected) in output.per_target.iter().zip(expected) {
+        assert!(
+            (val - expected).abs() < 0.1,
+            "target {i}: {val} != {expected}"
+        );
+    }
+    assert!((output.total - expected.iter().sum::<f32>()).abs() < 0.1);
+
+    // The reaction is derived from the aura again afterwards.
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Hydro));
+    assert_eq!(
+        calc.get(&L::Reaction.into()),
+        Some(ElementalReaction::Swirl(Element::Hydro)).calcindex()
+    );
+}
+
+#[test]
+fn anemo_hit_is_elemental() {
+    let targets = [
+        Target::new(Enemy::new(90.0).with_res(Element::Anemo.into(), 0.5)).with_aura(Aura::Pyro),
+    ];
+    let mut calc = attacker();
+    calc.set_select(GCK::L(L::Attribute), Attribute::from(Element::Anemo));
+    calc.set(
+        StatType::DMGMult(Some(Attribute::from(Element::Anemo).into())).into(),
+        0.5,
+    );
+    // The hit deals Anemo damage with the Anemo DMG bonus and RES, and its Swirl deals Pyro damage.
+    let hit = calc
+        .evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[0])
+        .unwrap();
+    assert_eq!(hit.total, 1000.0 * 1.5 * 0.5 * 0.5);
+    targets[0].write_to(&mut calc);
+    assert_eq!(
+        calc.get(&L::Reaction.into()),
+        Some(ElementalReaction::Swirl(Element::Pyro)).calcindex()
+    );
+    let swirl = calc
+        .evaluate_targets(&B::TransformativeRxnOutput.into(), &targets, &[0])
+        .unwrap();
+    assert!((swirl.total - 868.1121 * 0.9).abs() < 0.1);
+}
+
+#[test]
+fn state_restored() {
+    let targets = [Target::new(Enemy::new(90.0)).with_aura(Aura::Pyro)];
+    let mut calc = attacker();
+    calc.set(L::TargetLevel.into(), 100.0);
+    calc.set_select(GCK::L(L::Aura), Some(Aura::Hydro));
+    calc.set_select(GCK::L(L::Reaction), None::<ElementalReaction>);
+    let before = calc.snapshot();
+    let alone = calc.get(&B::DamageInstanceOutput.into());
+
+    assert_eq!(
+        calc.evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[0, 1]),
+        None
+    );
+    assert_eq!(calc.evaluate_swirl(&targets, 1, &[0]), None);
+    assert_eq!(calc.evaluate_swirl(&targets, 0, &[1]), None);
+    assert_eq!(calc.snapshot(), before);
+
+    calc.evaluate_targets(&B::DamageInstanceOutput.into(), &targets, &[0])
+        .unwrap();
+    calc.evaluate_swirl(&targets, 0, &[0]).unwrap();
+    assert_eq!(calc.snapshot(), before);
+    assert_eq!(calc.get(&B::DamageInstanceOutput.into()), alone);
+}
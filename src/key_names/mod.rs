@@ -0,0 +1,252 @@
+//! Stable, human readable names for stats and calculator keys, for instance `DMGMult:Pyro`.
+//!
+//! Names are built from the variant names, with the payload of a variant following a `:`. They are
+//! used when serializing, so that saved files do not depend on the order of the variants.
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    calculator::SnapshotKey,
+    damage::{Attribute, Category, CritMode},
+    element::{
+        reaction::{ElementalReaction, ReactionGroup},
+        Aura, Element,
+    },
+    gi_calculator::gi_rules::{B, GCK, L, S},
+    stats::{Condition, Type as StatType},
+};
+
+pub trait KeyName: Sized {
+    /// The name of the value.
+    fn name(&self) -> String;
+
+    /// Parses a name created by [`KeyName::name`].
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+/// Joins the name of a variant and the name of its payload. Empty payloads are left out.
+fn join_name(variant: &str, payload: String) -> String {
+    if payload.is_empty() {
+        variant.to_string()
+    } else {
+        format!("{variant}:{payload}")
+    }
+}
+
+/// Implements KeyName for enums, given their unit variants and then their variants with one field.
+/// Leaving out a variant results in a non-exhaustive match.
+macro_rules! key_names {
+    ($($t:ty { $($u:ident),* $(,)? } $({ $($v:ident($p:ty)),+ $(,)? })?)*) => {$(
+        impl KeyName for $t {
+            fn name(&self) -> String {
+                match self {
+                    $(Self::$u => stringify!($u).to_string(),)*
+                    $($(Self::$v(x) => join_name(stringify!($v), x.name()),)+)?
+                }
+            }
+            fn from_name(name: &str) -> Option<Self> {
+                let (head, rest) = name.split_once(':').unwrap_or((name, ""));
+                match head {
+                    $(stringify!($u) if rest.is_empty() => Some(Self::$u),)*
+                    $($(stringify!($v) => <$p>::from_name(rest).map(Self::$v),)+)?
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+key_names!(
+    Element { Anemo, Geo, Electro, Dendro, Hydro, Pyro, Cryo }
+    Category { NormalAttack, ChargedAttack, PlungeAttack, ElementalSkill, ElementalBurst }
+    CritMode { NonCrit, AvgCrit, OnCrit }
+    Aura {
+        Electro, Dendro, Hydro, Pyro, Cryo, Catalyzed, BloomCore, Frozen, Burning, ElectroCharged,
+    }
+    ElementalReaction {
+        Quicken, Aggravate, Spread, ElectroCharged, Overloaded, Superconduct, Bloom, Hyperbloom,
+        Burgeon, Burning, ForwardVaporize, ReverseVaporize, Freeze, Shatter, ForwardMelt,
+        ReverseMelt,
+    } {
+        Swirl(Element),
+        Crystallize(Element),
+    }
+    ReactionGroup { Vaporize, Melt, Swirl, Crystallize, Amplifying, Additive, Transformative }
+    StatType {
+        Level, MaxHP, Atk, Def, BaseHP, BaseAtk, BaseDef, HPPercent, AtkPercent, DefPercent,
+        FlatHP, FlatAtk, FlatDef, ElementalMastery, EnergyRecharge, CooldownReduction,
+        ShieldStrength, DMGReduction, HealingBonus, IncomingHealingBonus,
+    } {
+        CritRate(Option<Condition>),
+        CritDmg(Option<Condition>),
+        DMGMult(Option<Condition>),
+        AttributeRES(Attribute),
+        BaseDMGMult(Option<Condition>),
+        BaseDMGFlat(Option<Condition>),
+        RxnDMGMult(ElementalReaction),
+        RxnGroupDMGMult(ReactionGroup),
+        RxnCritRate(ElementalReaction),
+        RxnCritDmg(ElementalReaction),
+        DefIgnore(Option<Condition>),
+    }
+    S { Atk, Def, MaxHP, EM }
+    B {
+        DamageInstanceOutput, NonCritOutput, OnCritOutput, AvgCritOutput, BaseDMGFinal,
+        BaseDMGPostMult, BaseDMGAdd, AttributeBaseDMGAdd, CategoryBaseDMGAdd, BaseDMGMult,
+        AttributeBaseDMGMult, CategoryBaseDMGMult, BaseDMG,
+        DMGBonusMult, AttributeDMGBonusMult, CategoryDMGBonusMult, TargetDEFMult,
+        TargetDEFRemaining, TotalDEFIgnore, AttributeDEFIgnore, CategoryDEFIgnore,
+        TargetRESMult, TargetRESFinal, TargetBaseRES, TargetAttributeRES, TargetAttributeRESReduct,
+        AmpRxnMult, PotentialAmpRxnMult, AmpRxnTotalBonusMult, AmpRxnEMMult, AmpRxnBonusMult,
+        AddRxnDMGAdd, AddRxnBaseDMG, AddRxnBaseMult, AddRxnTotalBonusMult, AddRxnEMMult,
+        AddRxnBonusMult,
+        CritMult, TotalCritRate, AttributeCritRate, CategoryCritRate, TotalCritDMG,
+        AttributeCritDMG, CategoryCritDMG, TransformativeRxnOutput, TransRxnBaseDMG,
+        TransRxnBaseMult, TransRxnTotalBonusMult, TransRxnEMMult, TransRxnBonusMult,
+        TransRxnRESMult, TransRxnRESFinal, TransRxnCritMult, TransRxnCritRate, TransRxnCritDMG,
+        TransRxnAmpMult, CharLevelMult, EnemyLevelMult, HealOutput, HealBase, HealBonusMult,
+        ShieldOutput, ShieldBase, ShieldStrengthMult, ShieldEffectiveHP, ShieldAbsorptionMult,
+        CrystallizeShieldOutput, CrystallizeShieldBase, CrystallizeEMMult,
+        CrystallizeShieldEffectiveHP, CrystallizeAbsorptionMult, IncomingDMGOutput, IncomingBaseDMG,
+        IncomingDMGMult, IncomingDEFMult, IncomingRESMult, IncomingRESFinal, IncomingDMGReductMult,
+        EffectiveHP,
+    } {
+        EvalScaling(S),
+        EvalHealScaling(S),
+        EvalShieldScaling(S),
+    }
+    L {
+        Attribute, Category, OtherScalingDMG, BaseDMGAdd, TargetDMGBonusMult, TargetLevel,
+        TargetDEFReduct,
+        FlatHeal, TargetIncomingHealingBonus, FlatShield, ShieldElement, IncomingAttribute,
+        IncomingScaling,
+        AbsorbedElement, Aura,
+        BaseAmpRxnMult, CritMode, Reaction,
+    } {
+        Scaling(S),
+        HealScaling(S),
+        ShieldScaling(S),
+        Stat(StatType),
+        TargetAttributeRES(Attribute),
+        TargetAttributeRESReduct(Attribute),
+    }
+    GCK {} {
+        B(B),
+        L(L),
+        A(usize),
+        C(u32),
+    }
+);
+
+/// Anonymous nodes and constants are numbered in the order the rules are built, so they are left
+/// out of snapshots.
+impl SnapshotKey for GCK {
+    fn snapshot_name(&self) -> Option<String> {
+        match self {
+            Self::A(_) | Self::C(_) => None,
+            _ => Some(self.name()),
+        }
+    }
+    fn from_snapshot_name(name: &str) -> Option<Self> {
+        Self::from_name(name).filter(|k| !matches!(k, Self::A(_) | Self::C(_)))
+    }
+}
+
+/// Physical, or the name of the element.
+impl KeyName for Attribute {
+    fn name(&self) -> String {
+        match self {
+            Self::Physical => "Physical".to_string(),
+            Self::Elemental(e) => e.name(),
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Physical" => Some(Self::Physical),
+            _ => Element::from_name(name).map(Self::Elemental),
+        }
+    }
+}
+
+/// The name of the attribute or category, which never share names.
+impl KeyName for Condition {
+    fn name(&self) -> String {
+        match self {
+            Self::Attribute(a) => a.name(),
+            Self::Category(c) => c.name(),
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        Attribute::from_name(name)
+            .map(Self::Attribute)
+            .or_else(|| Category::from_name(name).map(Self::Category))
+    }
+}
+
+/// None has an empty name, so that `DMGMult(None)` is named `DMGMult`.
+impl<T: KeyName> KeyName for Option<T> {
+    fn name(&self) -> String {
+        self.as_ref().map(T::name).unwrap_or_default()
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        if name.is_empty() {
+            Some(None)
+        } else {
+            T::from_name(name).map(Some)
+        }
+    }
+}
+
+impl KeyName for usize {
+    fn name(&self) -> String {
+        self.to_string()
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        name.parse().ok()
+    }
+}
+impl KeyName for u32 {
+    fn name(&self) -> String {
+        self.to_string()
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        name.parse().ok()
+    }
+}
+
+/// Implements Serialize and Deserialize through KeyName.
+macro_rules! serde_by_name {
+    ($($t:ty),*) => {$(
+        impl Serialize for $t {
+            fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+                serializer.serialize_str(&self.name())
+            }
+        }
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+                let name = String::deserialize(deserializer)?;
+                Self::from_name(&name)
+                    .ok_or_else(|| De::Error::custom(format!("unknown name `{name}`")))
+            }
+        }
+    )*};
+}
+
+serde_by_name!(
+    Element,
+    Attribute,
+    Category,
+    CritMode,
+    Condition,
+    Aura,
+    ElementalReaction,
+    ReactionGroup,
+    StatType,
+    S,
+    B,
+    L,
+    GCK
+);
+
+#[cfg(test)]
+mod tests;
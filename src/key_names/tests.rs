@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    calculator::{Calculator, UnknownKeyName},
+    damage::{Attribute, Category},
+    element::{reaction::ElementalReaction, Element},
+    stats::{Condition, StatSheet, Type as StatType},
+    B, GCK, GI_RULES, L, S,
+};
+
+use super::KeyName;
+
+#[test]
+fn names() {
+    assert_eq!(
+        StatType::DMGMult(Some(Element::Pyro.into())).name(),
+        "DMGMult:Pyro"
+    );
+    assert_eq!(StatType::DMGMult(None).name(), "DMGMult");
+    assert_eq!(
+        StatType::AttributeRES(Attribute::Physical).name(),
+        "AttributeRES:Physical"
+    );
+    assert_eq!(
+        GCK::L(L::Stat(StatType::BaseDMGMult(Some(
+            Category::ElementalBurst.into()
+        ))))
+        .name(),
+        "L:Stat:BaseDMGMult:ElementalBurst"
+    );
+    assert_eq!(GCK::B(B::EvalScaling(S::Atk)).name(), "B:EvalScaling:Atk");
+}
+
+#[test]
+fn names_round_trip() {
+    let keys = [
+        GCK::L(L::Stat(StatType::Level)),
+        GCK::L(L::Stat(StatType::DMGMult(None))),
+        GCK::L(L::Stat(StatType::DefIgnore(Some(Condition::Category(
+            Category::ChargedAttack,
+        ))))),
+        GCK::L(L::Stat(StatType::RxnDMGMult(
+            ElementalReaction::ReverseMelt,
+        ))),
+        GCK::L(L::Stat(StatType::RxnDMGMult(ElementalReaction::Swirl(
+            Element::Pyro,
+        )))),
+        GCK::L(L::TargetAttributeRESReduct(Element::Cryo.into())),
+        GCK::L(L::Reaction),
+        GCK::B(B::DamageInstanceOutput),
+        GCK::A(12),
+        GCK::C(1.5f32.to_bits()),
+    ];
+    for key in keys {
+        assert_eq!(GCK::from_name(&key.name()), Some(key));
+    }
+    assert_eq!(GCK::from_name("L:Stat:DMGMult:Pyrro"), None);
+    assert_eq!(StatType::from_name("Level:Pyro"), None);
+}
+
+#[test]
+fn stat_sheet_json() {
+    let stats = StatSheet::from([
+        (StatType::CritRate(None), 0.75),
+        (StatType::DMGMult(Some(Element::Pyro.into())), 0.5),
+    ]);
+    let json = serde_json::to_string(&stats).unwrap();
+    assert!(json.contains("\"DMGMult:Pyro\":0.5"));
+    assert_eq!(serde_json::from_str::<StatSheet>(&json).unwrap(), stats);
+}
+
+#[test]
+fn stat_sheet_toml() {
+    let stats: StatSheet = toml::from_str(
+        r#"
+        Level = 90.0
+        "DMGMult:NormalAttack" = 0.25
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        stats,
+        StatSheet::from([
+            (StatType::Level, 90.0),
+            (StatType::DMGMult(Some(Category::NormalAttack.into())), 0.25),
+        ])
+    );
+    assert_eq!(
+        toml::from_str::<StatSheet>(&toml::to_string(&stats).unwrap()).unwrap(),
+        stats
+    );
+}
+
+#[test]
+fn calculator_snapshot() {
+    let mut calc = Calculator::new(&GI_RULES);
+    calc.set(StatType::Atk.into(), 1000.0);
+    calc.set(L::Scaling(S::Atk).into(), 2.0);
+    let base = calc.get(&GCK::B(B::BaseDMG));
+    // Anonymous nodes change along with the rules, so they are not saved.
+    calc.set(GCK::A(3), 1.0);
+
+    let json = serde_json::to_string(&calc.snapshot()).unwrap();
+    // Only the values that were set, in the order of their names.
+    assert_eq!(json, r#"{"L:Scaling:Atk":2.0,"L:Stat:Atk":1000.0}"#);
+    let mut restored = Calculator::new(&GI_RULES);
+    restored
+        .restore(&serde_json::from_str(&json).unwrap())
+        .unwrap();
+    assert_eq!(restored.get(&GCK::B(B::BaseDMG)), base);
+    restored.set(StatType::Atk.into(), 500.0);
+    assert_eq!(restored.get(&GCK::B(B::BaseDMG)), 1000.0);
+
+    let unknown = BTreeMap::from([("L:Stat:Strength".to_string(), 1.0)]);
+    assert_eq!(
+        restored.restore(&unknown),
+        Err(UnknownKeyName("L:Stat:Strength".to_string()))
+    );
+    assert_eq!(restored.get(&GCK::B(B::BaseDMG)), 1000.0);
+    let anon = BTreeMap::from([("A:3".to_string(), 1.0)]);
+    assert_eq!(
+        restored.restore(&anon),
+        Err(UnknownKeyName("A:3".to_string()))
+    );
+}
@@ -0,0 +1,74 @@
+//! Level multipliers, which transformative and additive reaction damage scale from, and other
+//! tables by level.
+
+/// Reaction level multipliers of characters, for levels 1 to 100.
+#[rustfmt::skip]
+pub const CHARACTER_LEVEL_MULTIPLIERS: [f32; 100] = [
+    17.165605, 18.535048, 19.904854, 21.274902, 22.6454,
+    24.649612, 26.640642, 28.868587, 31.36768, 34.143345,
+    37.201, 40.66, 44.446667, 48.56352, 53.74848,
+    59.081898, 64.420044, 69.72446, 75.12314, 80.58478,
+    86.11203, 91.70374, 97.24463, 102.812645, 108.40956,
+    113.20169, 118.102905, 122.97932, 129.72733, 136.29291,
+    142.67085, 149.02902, 155.41699, 161.8255, 169.10631,
+    176.51808, 184.07274, 191.70952, 199.55692, 207.38205,
+    215.3989, 224.16566, 233.50217, 243.35057, 256.06308,
+    268.5435, 281.52606, 295.01364, 309.0672, 323.6016,
+    336.75754, 350.5303, 364.4827, 378.61917, 398.6004,
+    416.39825, 434.387, 452.95105, 472.60623, 492.8849,
+    513.56854, 539.1032, 565.51056, 592.53876, 624.4434,
+    651.47015, 679.4968, 707.79407, 736.67145, 765.64026,
+    794.7734, 824.67737, 851.1578, 877.74207, 914.2291,
+    946.74677, 979.4114, 1011.223, 1044.7917, 1077.4437,
+    1109.9976, 1142.9766, 1176.3695, 1210.1844, 1253.8357,
+    1288.9528, 1325.4841, 1363.4569, 1405.0974, 1446.8535,
+    1488.2156, 1528.4446, 1580.3679, 1630.8475, 1711.1995,
+    1780.4487, 1847.3206, 1911.4742, 1972.8644, 2030.0718,
+];
+
+/// Level multipliers of enemies, for levels 1 to 100, used for the reaction damage they deal.
+/// These match the character table up to level 90.
+#[rustfmt::skip]
+pub const ENEMY_LEVEL_MULTIPLIERS: [f32; 100] = {
+    let mut table = CHARACTER_LEVEL_MULTIPLIERS;
+    let above_90 = [
+        1470.3047, 1493.4909, 1516.9375, 1541.0696, 1561.468,
+        1584.3741, 1606.0065, 1628.3441, 1651.1395, 1674.8092,
+    ];
+    let mut i = 0;
+    while i < above_90.len() {
+        table[90 + i] = above_90[i];
+        i += 1;
+    }
+    table
+};
+
+/// Base HP of the shards created by Crystallize, by the level of the character that triggered it.
+/// Only the levels taken from the game data are listed.
+///
+/// TODO - Add the other levels from the game data.
+pub const CRYSTALLIZE_SHIELD_BASE: [(u32, f32); 2] = [(1, 91.1791), (90, 1851.0604)];
+
+/// Looks up a level in a table, rounding down and clamping it to the levels in the table.
+fn lookup(table: &[f32; 100], level: f32) -> f32 {
+    let index = (level.floor() as usize).clamp(1, table.len()) - 1;
+    table[index]
+}
+
+pub fn character_level_multiplier(level: f32) -> f32 {
+    lookup(&CHARACTER_LEVEL_MULTIPLIERS, level)
+}
+
+pub fn enemy_level_multiplier(level: f32) -> f32 {
+    lookup(&ENEMY_LEVEL_MULTIPLIERS, level)
+}
+
+/// The base HP of Crystallize shards at the level, rounded down, or `None` for the levels missing
+/// from `CRYSTALLIZE_SHIELD_BASE`.
+pub fn crystallize_shield_base(level: f32) -> Option<f32> {
+    let level = level.floor() as u32;
+    CRYSTALLIZE_SHIELD_BASE
+        .iter()
+        .find(|&&(l, _)| l == level)
+        .map(|&(_, base)| base)
+}
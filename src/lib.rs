@@ -1,5 +1,7 @@
 // TODO - Remove once completed
 #![allow(dead_code)]
+// The rule_gen! macro recurses over the tokens of all its rules, a few tokens at a time.
+#![recursion_limit = "512"]
 
 pub mod calculator;
 pub mod gi_calculator;
@@ -8,8 +10,12 @@ pub mod character;
 pub mod damage;
 pub mod damage_calculator;
 pub mod element;
+pub mod enemy;
 pub mod equipment;
+pub mod key_names;
+pub mod level_multiplier;
 pub mod stats;
+pub mod validation;
 
 // Uncategorized genshin data types
 enum Ascension {
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
 use giopt::{
-    calculator::Calculator,
+    calculator::{select::Select, Calculator},
     damage::{Attribute, Category},
-    element::{reaction::ElementalReaction::*, Element::*},
+    element::{Aura, Element::*},
     stats::{StatSheet, Type::*},
     B, GCK, GI_RULES, L, S,
 };
@@ -17,8 +17,8 @@ fn main() {
         (Atk, 4514.2),
         (Def, 765.0),
         (ElementalMastery, 380.0),
-        (CritRate, 0.772),
-        (CritDmg, 1.918),
+        (CritRate(None), 0.772),
+        (CritDmg(None), 1.918),
         (DMGMult(Some(Pyro.into())), 1.416),
         (DMGMult(None), 0.18),
     ]);
@@ -47,10 +47,6 @@ fn main() {
 
     // println!("damage on crit: {dmg_on_crit}");
 
-    // You have to create an instance of the GI_RULES so that the LazyLock gets evaluated.
-    // Actually I'm not quite sure about this, the type is still LazyLock<Rules<GCK>>
-    let rules = GI_RULES;
-
     // GI_RULES testing
     let mut calc = Calculator::from_components(
         HashMap::from([
@@ -60,20 +56,19 @@ fn main() {
             (GCK::L(L::Scaling(S::Atk)), 9.0),
             (GCK::L(L::Attribute), Attribute::from(Pyro).calcindex()),
             (GCK::L(L::Category), Category::NormalAttack.calcindex()),
-            (GCK::L(L::BaseAmpRxnMult), 2.0),
-            (GCK::L(L::AmpRxnType), ForwardMelt.amp_rxn_type_calcindex()),
+            (GCK::L(L::Aura), Some(Aura::Cryo).calcindex()),
             // (Level.into(), 90.0),
             // (MaxHP.into(), 20626.0),
             // (Atk.into(), 4514.2),
             // (Def.into(), 765.0),
             // (ElementalMastery.into(), 380.0),
-            // (CritRate.into(), 0.772),
-            // (CritDmg.into(), 1.918),
+            // (CritRate(None).into(), 0.772),
+            // (CritDmg(None).into(), 1.918),
             // (DMGMult(None).into(), 0.18),
             // (DMGMult(Some(Pyro.into())).into(), 1.416),
             // (DMGMult(Some(Cryo.into())).into(), 0.40),
         ]),
-        &rules,
+        &GI_RULES,
     );
 
     // testing import_stat_sheet.
@@ -1,13 +1,24 @@
 use crate::{
     damage,
-    element::{reaction::ElementalReaction, Element},
+    element::{
+        reaction::{ElementalReaction, ReactionGroup},
+        Element,
+    },
 };
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     ops::{Mul, MulAssign},
 };
 
-#[derive(Clone, Debug, PartialEq)]
+mod ops;
+mod parse;
+pub use ops::{StatChange, StatSheetDiff};
+pub use parse::ParseStatError;
+
+/// Serialized as a map from stat names to values, see [`crate::key_names`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct StatSheet {
     data: HashMap<Type, f32>,
 }
@@ -53,7 +64,7 @@ impl<const N: usize> From<[(Type, f32); N]> for StatSheet {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Stat {
     typ: Type,
     val: f32,
@@ -85,27 +96,44 @@ impl MulAssign<f32> for Stat {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Type {
     Level,
 
+    // Totals, usually derived from the components below. Setting them directly overrides that.
     MaxHP,
     Atk,
     Def,
 
+    // Base stats, from the character and the weapon. Buffs that scale from base ATK use these.
+    BaseHP,
+    BaseAtk,
+    BaseDef,
+
+    // Percentage bonuses, multiplying the base stats.
+    HPPercent,
+    AtkPercent,
+    DefPercent,
+
+    // Flat bonuses, added after the percentage bonuses.
+    FlatHP,
+    FlatAtk,
+    FlatDef,
+
     ElementalMastery,
 
-    // Conditional Crit Stats exist. It's actually possible
-    // that all stats can be conditional... Not sure what to
-    // do with that.
-    CritRate,
-    CritDmg,
+    // Conditional Crit Stats exist, for instance for charged attacks only. It's actually
+    // possible that all stats can be conditional... Not sure what to do with that.
+    CritRate(Option<Condition>),
+    CritDmg(Option<Condition>),
 
     EnergyRecharge,
 
     CooldownReduction,
 
     ShieldStrength,
+    // Reduces the damage taken, after DEF and RES.
+    DMGReduction,
 
     HealingBonus,
     IncomingHealingBonus,
@@ -121,15 +149,41 @@ pub enum Type {
     // Reaction DMG bonuses are dealt with in the Reaction effect, and thus
     // I think they warrant their own type of stat rather than being handled with any condition.
     RxnDMGMult(ElementalReaction),
-
-    // TODO - Def Ignore? Def Shred? Enemy Debuffs? Character Temporary Buffs?
-    // Def ignore should be a character stat because it's character-specific.
-    // Def shred and Def Ignore stack multiplicatively
+    // Applies to every reaction of the group, on top of the bonuses for the reaction itself.
+    RxnGroupDMGMult(ReactionGroup),
+    // Only transformative reactions of some characters can crit.
+    RxnCritRate(ElementalReaction),
+    RxnCritDmg(ElementalReaction),
+
+    // TODO - Enemy Debuffs? Character Temporary Buffs?
+    // Def ignore should be a character stat because it's character-specific, while Def shred is
+    // on the target. Def shred and Def Ignore stack multiplicatively
     DefIgnore(Option<Condition>),
 }
 
+impl Type {
+    /// Whether the stat is a percentage, and is written with a `%` when parsing or displaying.
+    pub fn is_percent(&self) -> bool {
+        !matches!(
+            *self,
+            Self::Level
+                | Self::MaxHP
+                | Self::Atk
+                | Self::Def
+                | Self::BaseHP
+                | Self::BaseAtk
+                | Self::BaseDef
+                | Self::FlatHP
+                | Self::FlatAtk
+                | Self::FlatDef
+                | Self::ElementalMastery
+                | Self::BaseDMGFlat(_)
+        )
+    }
+}
+
 // For Stats that only apply to some types of damage
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Condition {
     Attribute(damage::Attribute),
     Category(damage::Category),
@@ -149,3 +203,6 @@ impl From<damage::Category> for Condition {
         Self::Category(value)
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,189 @@
+//! Combining and comparing stat sheets, for instance adding up the base stats, weapon, artifacts
+//! and buffs of a character, or comparing two builds.
+
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use super::{Condition, Stat, StatSheet, Type};
+
+/// Sums of stats smaller than this fraction of their terms are rounding errors, like the residue
+/// of 0.1 + 0.6 - 0.7.
+const ROUNDING_TOLERANCE: f32 = 4.0 * f32::EPSILON;
+
+impl StatSheet {
+    /// Every stat in the sheet, in the order of their types.
+    pub fn iter(&self) -> impl Iterator<Item = Stat> {
+        let mut stats: Vec<_> = self
+            .data
+            .iter()
+            .map(|(&typ, &val)| Stat::new(typ, val))
+            .collect();
+        stats.sort_by_key(Stat::typ);
+        stats.into_iter()
+    }
+
+    /// Adds every stat of the other sheet to this one. Stats that add up to 0, give or take
+    /// rounding errors, are removed, as missing stats are 0, so that sheets with the same stats
+    /// compare equal. Levels don't add up: the sheet keeps its own, or takes the other's if it has
+    /// none.
+    pub fn merge(&mut self, other: &StatSheet) {
+        for (&typ, &val) in other.data.iter() {
+            if typ == Type::Level {
+                self.data.entry(typ).or_insert(val);
+                continue;
+            }
+            let old = self.get(typ);
+            let sum = old + val;
+            if sum.abs() <= ROUNDING_TOLERANCE * old.abs().max(val.abs()) {
+                self.data.remove(&typ);
+            } else {
+                self.data.insert(typ, sum);
+            }
+        }
+    }
+
+    /// A copy of the sheet with every stat but the level multiplied by the factor. Scaling by 0
+    /// leaves only the level.
+    pub fn scale(&self, factor: f32) -> Self {
+        self * factor
+    }
+
+    /// Keeps the stats for which the predicate returns true.
+    pub fn filter(&self, predicate: impl Fn(&Stat) -> bool) -> Self {
+        Self {
+            data: self
+                .iter()
+                .filter(|stat| predicate(stat))
+                .map(|stat| (stat.typ(), stat.val()))
+                .collect(),
+        }
+    }
+
+    /// Keeps the stats with exactly this condition. `None` keeps the stats that always apply.
+    pub fn filter_condition(&self, condition: Option<Condition>) -> Self {
+        self.filter(|stat| stat.typ().condition() == condition)
+    }
+
+    /// Lists the stats that were added, removed or changed going from this sheet to the other.
+    pub fn diff(&self, other: &StatSheet) -> StatSheetDiff {
+        let mut diff = StatSheetDiff::default();
+        for stat in self.iter() {
+            match other.data.get(&stat.typ()) {
+                None => diff.removed.push(stat),
+                Some(&to) if to != stat.val() => diff.changed.push(StatChange {
+                    typ: stat.typ(),
+                    from: stat.val(),
+                    to,
+                }),
+                Some(_) => (),
+            }
+        }
+        diff.added = other
+            .iter()
+            .filter(|stat| !self.data.contains_key(&stat.typ()))
+            .collect();
+        diff
+    }
+}
+
+impl Type {
+    /// The condition under which the stat applies, if it has one.
+    pub fn condition(&self) -> Option<Condition> {
+        match *self {
+            Self::CritRate(c)
+            | Self::CritDmg(c)
+            | Self::DMGMult(c)
+            | Self::BaseDMGMult(c)
+            | Self::BaseDMGFlat(c)
+            | Self::DefIgnore(c) => c,
+            Self::AttributeRES(a) => Some(Condition::Attribute(a)),
+            _ => None,
+        }
+    }
+}
+
+/// The differences between two stat sheets, each list in the order of the stat types.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatSheetDiff {
+    pub added: Vec<Stat>,
+    pub removed: Vec<Stat>,
+    pub changed: Vec<StatChange>,
+}
+impl StatSheetDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatChange {
+    pub typ: Type,
+    pub from: f32,
+    pub to: f32,
+}
+impl StatChange {
+    pub fn delta(&self) -> f32 {
+        self.to - self.from
+    }
+}
+
+impl Add<&StatSheet> for &StatSheet {
+    type Output = StatSheet;
+    fn add(self, rhs: &StatSheet) -> Self::Output {
+        let mut result = self.clone();
+        result.merge(rhs);
+        result
+    }
+}
+impl AddAssign<&StatSheet> for StatSheet {
+    fn add_assign(&mut self, rhs: &StatSheet) {
+        self.merge(rhs);
+    }
+}
+impl Sub<&StatSheet> for &StatSheet {
+    type Output = StatSheet;
+    fn sub(self, rhs: &StatSheet) -> Self::Output {
+        let mut result = self.clone();
+        result -= rhs;
+        result
+    }
+}
+impl SubAssign<&StatSheet> for StatSheet {
+    fn sub_assign(&mut self, rhs: &StatSheet) {
+        self.merge(&-rhs);
+    }
+}
+impl Neg for &StatSheet {
+    type Output = StatSheet;
+    fn neg(self) -> Self::Output {
+        self * -1.0
+    }
+}
+impl Mul<f32> for &StatSheet {
+    type Output = StatSheet;
+    fn mul(self, rhs: f32) -> Self::Output {
+        let mut result = self.clone();
+        result *= rhs;
+        result
+    }
+}
+impl MulAssign<f32> for StatSheet {
+    fn mul_assign(&mut self, rhs: f32) {
+        self.data.retain(|&typ, val| {
+            if typ != Type::Level {
+                *val *= rhs;
+            }
+            *val != 0.0
+        });
+    }
+}
+impl<'a> Sum<&'a StatSheet> for StatSheet {
+    fn sum<I: Iterator<Item = &'a StatSheet>>(iter: I) -> Self {
+        iter.fold(Self::default(), |mut acc, sheet| {
+            acc += sheet;
+            acc
+        })
+    }
+}
@@ -0,0 +1,239 @@
+//! Short names for stats, for instance `crit_rate`, `dmg_bonus[pyro]` or `dmg_bonus[normal_attack]`,
+//! and stat sheets written as lines of `name = value`.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use super::{Condition, Stat, StatSheet, Type};
+use crate::{
+    calculator::select::Select,
+    damage::{Attribute, Category},
+    element::reaction::{ElementalReaction, ReactionGroup},
+    key_names::KeyName,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseStatError {
+    UnknownStat(String),
+    UnknownCondition(String),
+    InvalidValue(String),
+    MissingValue(String),
+    /// Only stats that are percentages can be written with a `%`.
+    NotPercent(Type),
+}
+impl Display for ParseStatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownStat(s) => write!(f, "unknown stat `{s}`"),
+            Self::UnknownCondition(s) => write!(f, "unknown condition `{s}`"),
+            Self::InvalidValue(s) => write!(f, "invalid value `{s}`"),
+            Self::MissingValue(s) => write!(f, "missing value for `{s}`, expected `name = value`"),
+            Self::NotPercent(t) => write!(f, "`{t}` is not a percentage"),
+        }
+    }
+}
+impl Error for ParseStatError {}
+
+/// Converts a name like `NormalAttack` into `normal_attack`, and `Swirl:Pyro` into `swirl_pyro`.
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.chars() {
+        if c == ':' {
+            result.push('_');
+            continue;
+        }
+        if c.is_uppercase() && !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+/// Finds the value of a [`Select`] type with the given snake case name.
+fn find_variant<T: Select + KeyName>(name: &str) -> Option<T> {
+    T::VARIANTS
+        .iter()
+        .find(|v| snake_case(&v.name()) == name)
+        .copied()
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&snake_case(&self.name()))
+    }
+}
+impl FromStr for Condition {
+    type Err = ParseStatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        find_variant::<Attribute>(s)
+            .map(Self::Attribute)
+            .or_else(|| find_variant::<Category>(s).map(Self::Category))
+            .ok_or_else(|| ParseStatError::UnknownCondition(s.to_string()))
+    }
+}
+
+/// Short names of the stats without a payload. Stats missing from here use their serialized name.
+const UNIT_NAMES: [(Type, &str); 20] = [
+    (Type::Level, "level"),
+    (Type::MaxHP, "max_hp"),
+    (Type::Atk, "atk"),
+    (Type::Def, "def"),
+    (Type::BaseHP, "base_hp"),
+    (Type::BaseAtk, "base_atk"),
+    (Type::BaseDef, "base_def"),
+    (Type::HPPercent, "hp_percent"),
+    (Type::AtkPercent, "atk_percent"),
+    (Type::DefPercent, "def_percent"),
+    (Type::FlatHP, "flat_hp"),
+    (Type::FlatAtk, "flat_atk"),
+    (Type::FlatDef, "flat_def"),
+    (Type::ElementalMastery, "elemental_mastery"),
+    (Type::EnergyRecharge, "energy_recharge"),
+    (Type::CooldownReduction, "cooldown_reduction"),
+    (Type::ShieldStrength, "shield_strength"),
+    (Type::DMGReduction, "dmg_reduction"),
+    (Type::HealingBonus, "healing_bonus"),
+    (Type::IncomingHealingBonus, "incoming_healing_bonus"),
+];
+
+/// Writes `name[payload]`, or only `name` if there is no payload.
+fn write_with(f: &mut Formatter<'_>, name: &str, payload: Option<impl Display>) -> fmt::Result {
+    match payload {
+        Some(p) => write!(f, "{name}[{p}]"),
+        None => f.write_str(name),
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::CritRate(c) => write_with(f, "crit_rate", c),
+            Self::CritDmg(c) => write_with(f, "crit_dmg", c),
+            Self::DMGMult(c) => write_with(f, "dmg_bonus", c),
+            Self::AttributeRES(a) => write_with(f, "res", Some(Condition::Attribute(a))),
+            Self::BaseDMGMult(c) => write_with(f, "base_dmg_bonus", c),
+            Self::BaseDMGFlat(c) => write_with(f, "base_dmg_flat", c),
+            Self::RxnDMGMult(r) => write_with(f, "rxn_dmg_bonus", Some(snake_case(&r.name()))),
+            Self::RxnGroupDMGMult(g) => write_with(f, "rxn_dmg_bonus", Some(snake_case(&g.name()))),
+            Self::RxnCritRate(r) => write_with(f, "rxn_crit_rate", Some(snake_case(&r.name()))),
+            Self::RxnCritDmg(r) => write_with(f, "rxn_crit_dmg", Some(snake_case(&r.name()))),
+            Self::DefIgnore(c) => write_with(f, "def_ignore", c),
+            unit => match UNIT_NAMES.iter().find(|(t, _)| *t == unit) {
+                Some((_, name)) => f.write_str(name),
+                None => f.write_str(&unit.name()),
+            },
+        }
+    }
+}
+
+/// Parses the short names, and also accepts the names used when serializing, like `Atk` or
+/// `DMGMult:Pyro`.
+impl FromStr for Type {
+    type Err = ParseStatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || ParseStatError::UnknownStat(s.to_string());
+        let (name, payload) = match s.split_once('[') {
+            Some((name, rest)) => (name, Some(rest.strip_suffix(']').ok_or_else(unknown)?)),
+            None => (s, None),
+        };
+        let condition = || payload.map(Condition::from_str).transpose();
+        let reaction =
+            || find_variant::<ElementalReaction>(payload.ok_or_else(unknown)?).ok_or_else(unknown);
+        let attribute = || {
+            let payload = payload.ok_or_else(unknown)?;
+            find_variant::<Attribute>(payload)
+                .ok_or_else(|| ParseStatError::UnknownCondition(payload.to_string()))
+        };
+        match name {
+            "crit_rate" => Ok(Self::CritRate(condition()?)),
+            "crit_dmg" => Ok(Self::CritDmg(condition()?)),
+            "dmg_bonus" => Ok(Self::DMGMult(condition()?)),
+            "res" => Ok(Self::AttributeRES(attribute()?)),
+            "base_dmg_bonus" => Ok(Self::BaseDMGMult(condition()?)),
+            "base_dmg_flat" => Ok(Self::BaseDMGFlat(condition()?)),
+            // Reactions and groups of reactions never share names.
+            "rxn_dmg_bonus" => reaction().map(Self::RxnDMGMult).or_else(|_| {
+                find_variant::<ReactionGroup>(payload.ok_or_else(unknown)?)
+                    .map(Self::RxnGroupDMGMult)
+                    .ok_or_else(unknown)
+            }),
+            "rxn_crit_rate" => reaction().map(Self::RxnCritRate),
+            "rxn_crit_dmg" => reaction().map(Self::RxnCritDmg),
+            "def_ignore" => Ok(Self::DefIgnore(condition()?)),
+            _ => UNIT_NAMES
+                .iter()
+                .find(|(_, n)| payload.is_none() && *n == name)
+                .map(|(t, _)| *t)
+                .or_else(|| Self::from_name(s))
+                .ok_or_else(unknown),
+        }
+    }
+}
+
+/// Rounds away the noise of converting f32 percentages, keeping 4 decimals.
+fn as_percent(val: f32) -> f64 {
+    (val as f64 * 1e6).round() / 1e4
+}
+
+/// Written as `name = value`, with percentages written as `77.2%`.
+impl Display for Stat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.typ.is_percent() {
+            write!(f, "{} = {}%", self.typ, as_percent(self.val))
+        } else {
+            write!(f, "{} = {}", self.typ, self.val)
+        }
+    }
+}
+
+/// Parses `name = value`. Values ending in `%` are divided by 100, which is only allowed for
+/// stats that are percentages.
+impl FromStr for Stat {
+    type Err = ParseStatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| ParseStatError::MissingValue(s.trim().to_string()))?;
+        let typ: Type = name.trim().parse()?;
+        let value = value.trim();
+        let invalid = || ParseStatError::InvalidValue(value.to_string());
+        let val = match value.strip_suffix('%') {
+            Some(percent) if typ.is_percent() => {
+                (percent.trim().parse::<f64>().map_err(|_| invalid())? / 100.0) as f32
+            }
+            Some(_) => return Err(ParseStatError::NotPercent(typ)),
+            None => value.parse().map_err(|_| invalid())?,
+        };
+        Ok(Self::new(typ, val))
+    }
+}
+
+/// One stat per line, in the order of [`StatSheet::iter`].
+impl Display for StatSheet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for stat in self.iter() {
+            writeln!(f, "{stat}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses one stat per line, see [`Stat`]. Empty lines and lines starting with `#` are skipped,
+/// and stats that appear more than once are added together.
+impl FromStr for StatSheet {
+    type Err = ParseStatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sheet = Self::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            sheet.add_stat(&line.parse()?);
+        }
+        Ok(sheet)
+    }
+}
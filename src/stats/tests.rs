@@ -0,0 +1,185 @@
+use crate::{
+    damage::{Attribute, Category},
+    element::{
+        reaction::{ElementalReaction, ReactionGroup},
+        Element,
+    },
+};
+
+use super::{Condition, ParseStatError, Stat, StatSheet, Type::*};
+
+#[test]
+fn type_names() {
+    let cases = [
+        (CritRate(None), "crit_rate"),
+        (BaseAtk, "base_atk"),
+        (AtkPercent, "atk_percent"),
+        (
+            CritDmg(Some(Category::ChargedAttack.into())),
+            "crit_dmg[charged_attack]",
+        ),
+        (DMGMult(None), "dmg_bonus"),
+        (DMGMult(Some(Element::Pyro.into())), "dmg_bonus[pyro]"),
+        (
+            DMGMult(Some(Category::NormalAttack.into())),
+            "dmg_bonus[normal_attack]",
+        ),
+        (AttributeRES(Attribute::Physical), "res[physical]"),
+        (
+            RxnDMGMult(ElementalReaction::ForwardMelt),
+            "rxn_dmg_bonus[forward_melt]",
+        ),
+        (
+            RxnDMGMult(ElementalReaction::Swirl(Element::Hydro)),
+            "rxn_dmg_bonus[swirl_hydro]",
+        ),
+        (
+            RxnGroupDMGMult(ReactionGroup::Vaporize),
+            "rxn_dmg_bonus[vaporize]",
+        ),
+        (RxnCritDmg(ElementalReaction::Bloom), "rxn_crit_dmg[bloom]"),
+        (
+            DefIgnore(Some(Condition::Category(Category::ElementalBurst))),
+            "def_ignore[elemental_burst]",
+        ),
+    ];
+    for (typ, name) in cases {
+        assert_eq!(typ.to_string(), name);
+        assert_eq!(name.parse(), Ok(typ));
+    }
+    assert_eq!(
+        "DMGMult:Pyro".parse(),
+        Ok(DMGMult(Some(Element::Pyro.into())))
+    );
+    assert!("dmg_bonus[pyrro]".parse::<super::Type>().is_err());
+    assert!("atk[pyro]".parse::<super::Type>().is_err());
+}
+
+#[test]
+fn stat_sheet_parse() {
+    let stats: StatSheet = "
+        # Arlecchino
+        Level = 90
+        CritRate = 77.2%
+        crit_dmg = 191.8%
+        dmg_bonus[pyro] = 1.416
+        atk = 4514.2
+        dmg_bonus = 10%
+        dmg_bonus = 8%
+    "
+    .parse()
+    .unwrap();
+    assert_eq!(
+        stats,
+        StatSheet::from([
+            (Level, 90.0),
+            (CritRate(None), 0.772),
+            (CritDmg(None), 1.918),
+            (DMGMult(Some(Element::Pyro.into())), 1.416),
+            (Atk, 4514.2),
+            (DMGMult(None), 0.18),
+        ])
+    );
+    assert_eq!(stats.to_string().parse::<StatSheet>(), Ok(stats));
+}
+
+#[test]
+fn stat_parse_errors() {
+    assert_eq!(
+        "atk = 5%".parse::<Stat>(),
+        Err(ParseStatError::NotPercent(Atk))
+    );
+    assert!(matches!(
+        "atk".parse::<Stat>(),
+        Err(ParseStatError::MissingValue(_))
+    ));
+    assert!(matches!(
+        "atk = lots".parse::<Stat>(),
+        Err(ParseStatError::InvalidValue(_))
+    ));
+    assert_eq!(
+        Stat::new(CritRate(None), 0.772).to_string(),
+        "crit_rate = 77.2%"
+    );
+}
+
+#[test]
+fn stat_sheet_arithmetic() {
+    let character = StatSheet::from([(Atk, 300.0), (CritRate(None), 0.05)]);
+    let weapon = StatSheet::from([(Atk, 600.0), (CritDmg(None), 0.5)]);
+    let total: StatSheet = [&character, &weapon].into_iter().sum();
+    assert_eq!(
+        total,
+        StatSheet::from([(Atk, 900.0), (CritRate(None), 0.05), (CritDmg(None), 0.5)])
+    );
+    // Stats that cancel out are removed.
+    assert_eq!(&total - &weapon, character);
+    assert!((&weapon - &weapon).diff(&StatSheet::default()).is_empty());
+    assert_eq!(total.scale(2.0).get(Atk), 1800.0);
+    assert_eq!(total.scale(0.0), StatSheet::default());
+
+    let types: Vec<_> = total.iter().map(|s| s.typ()).collect();
+    assert_eq!(types, vec![Atk, CritRate(None), CritDmg(None)]);
+
+    // Rounding errors are removed too.
+    let a = StatSheet::from([(DMGMult(None), 0.1)]);
+    let b = StatSheet::from([(DMGMult(None), 0.6)]);
+    let c = StatSheet::from([(DMGMult(None), 0.7)]);
+    assert_eq!(&(&a + &b) - &c, StatSheet::default());
+}
+
+#[test]
+fn stat_sheet_level_not_added() {
+    let character = StatSheet::from([(Level, 90.0), (Atk, 300.0)]);
+    let buff = StatSheet::from([(Level, 80.0), (Atk, 100.0)]);
+    assert_eq!(
+        &character + &character,
+        StatSheet::from([(Level, 90.0), (Atk, 600.0)])
+    );
+    assert_eq!((&character + &buff).get(Level), 90.0);
+    assert_eq!((&StatSheet::default() + &buff).get(Level), 80.0);
+    assert_eq!(&character - &character, StatSheet::from([(Level, 90.0)]));
+    assert_eq!(
+        character.scale(2.0),
+        StatSheet::from([(Level, 90.0), (Atk, 600.0)])
+    );
+    assert_eq!(character.scale(0.0), StatSheet::from([(Level, 90.0)]));
+}
+
+#[test]
+fn stat_sheet_filter_condition() {
+    let stats = StatSheet::from([
+        (DMGMult(None), 0.1),
+        (DMGMult(Some(Element::Pyro.into())), 0.466),
+        (BaseDMGFlat(Some(Element::Pyro.into())), 100.0),
+        (DMGMult(Some(Category::ElementalBurst.into())), 0.2),
+    ]);
+    assert_eq!(
+        stats.filter_condition(Some(Element::Pyro.into())),
+        StatSheet::from([
+            (DMGMult(Some(Element::Pyro.into())), 0.466),
+            (BaseDMGFlat(Some(Element::Pyro.into())), 100.0),
+        ])
+    );
+    assert_eq!(
+        stats.filter_condition(None),
+        StatSheet::from([(DMGMult(None), 0.1)])
+    );
+}
+
+#[test]
+fn stat_sheet_diff() {
+    let old = StatSheet::from([
+        (Atk, 2000.0),
+        (CritRate(None), 0.6),
+        (ElementalMastery, 100.0),
+    ]);
+    let new = StatSheet::from([(Atk, 2200.0), (CritRate(None), 0.6), (CritDmg(None), 1.2)]);
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, vec![Stat::new(CritDmg(None), 1.2)]);
+    assert_eq!(diff.removed, vec![Stat::new(ElementalMastery, 100.0)]);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].typ, Atk);
+    assert_eq!(diff.changed[0].delta(), 200.0);
+    assert!(new.diff(&new).is_empty());
+}
@@ -0,0 +1,226 @@
+//! Damage numbers observed in game, and a harness that checks the formulas against them.
+//!
+//! Every case is run through a [`Calculator`] on [`GI_RULES`], and reports the intermediate `B`
+//! values of the calculator so that mismatches can be tracked down. The legacy
+//! [`damage_calculator`](crate::damage_calculator) is run too, for comparison only.
+//!
+//! No hits have been recorded in game yet, so `cases.toml` has no cases and the formulas have not
+//! been validated against the game.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::LazyLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calculator::{select::Select, Calculator},
+    character::talent::{Talent, ICD},
+    damage::{Attribute, Category, CritMode},
+    damage_calculator::{evaluate_damage_instance, TRACED},
+    element::{reaction::ElementalReaction, Aura, ElementalApplication, GaugedAura},
+    enemy::Enemy,
+    stats::{Stat, StatSheet, Type as StatType},
+    B, GCK, GI_RULES, L, S,
+};
+
+/// The tolerance of cases that don't set one. The game rounds damage to whole numbers.
+pub const DEFAULT_TOLERANCE: f32 = 1.0;
+
+/// One hit seen in game, with everything needed to calculate it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidationCase {
+    pub stats: StatSheet,
+    /// The multipliers of the talent, for each stat it scales off.
+    pub scaling: HashMap<S, f32>,
+    pub attribute: Attribute,
+    pub category: Category,
+    pub enemy: Enemy,
+    #[serde(default)]
+    pub res_reduct: HashMap<Attribute, f32>,
+    #[serde(default)]
+    pub def_reduct: f32,
+    #[serde(default)]
+    pub aura: Option<Aura>,
+    /// The reaction seen in game. It is checked against the one the rules derive from the aura.
+    #[serde(default)]
+    pub reaction: Option<ElementalReaction>,
+    pub crit_mode: CritMode,
+    pub observed: f32,
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+}
+
+fn default_tolerance() -> f32 {
+    DEFAULT_TOLERANCE
+}
+
+static CASES: LazyLock<BTreeMap<String, ValidationCase>> = LazyLock::new(|| {
+    load_cases(include_str!("cases.toml")).expect("bundled validation cases should be valid")
+});
+
+/// Parses a table of cases keyed by name, in the format of the bundled `cases.toml`.
+pub fn load_cases(text: &str) -> Result<BTreeMap<String, ValidationCase>, toml::de::Error> {
+    toml::from_str(text)
+}
+
+/// The cases bundled with the crate, by name.
+pub fn bundled_cases() -> &'static BTreeMap<String, ValidationCase> {
+    &CASES
+}
+
+/// Runs every case, in the order of their names.
+pub fn run_cases(cases: &BTreeMap<String, ValidationCase>) -> Vec<CaseReport> {
+    cases.iter().map(|(name, case)| case.run(name)).collect()
+}
+
+impl ValidationCase {
+    /// A calculator with every input of the case set.
+    pub fn calculator(&self) -> Calculator<'static, GCK> {
+        let mut calc = Calculator::new(&GI_RULES);
+        calc.import_stat_sheet(&self.stats);
+        for (s, &val) in self.scaling.iter() {
+            calc.set(L::Scaling(s.clone()).into(), val);
+        }
+        calc.set_select(GCK::L(L::Attribute), self.attribute);
+        calc.set_select(GCK::L(L::Category), self.category);
+        self.enemy.write_to(&mut calc);
+        for (&a, &val) in self.res_reduct.iter() {
+            calc.set(L::TargetAttributeRESReduct(a).into(), val);
+        }
+        calc.set(L::TargetDEFReduct.into(), self.def_reduct);
+        calc.set_select(GCK::L(L::Aura), self.aura);
+        calc.set_select(GCK::L(L::CritMode), self.crit_mode);
+        calc
+    }
+
+    /// The damage according to the legacy damage calculator, which has no DEF shred and no DEF
+    /// multiplier overrides, so it is off for cases that have them.
+    pub fn legacy_damage(&self) -> f32 {
+        let scalings = self
+            .scaling
+            .iter()
+            .map(|(s, &val)| {
+                let typ = match s {
+                    S::Atk => StatType::Atk,
+                    S::Def => StatType::Def,
+                    S::MaxHP => StatType::MaxHP,
+                    S::EM => StatType::ElementalMastery,
+                };
+                Stat::new(typ, val)
+            })
+            .collect();
+        let talent = Talent::new(
+            Some(self.category),
+            Some(self.attribute),
+            &ICD::STANDARD,
+            scalings,
+        );
+        let elem_app = match self.attribute {
+            Attribute::Elemental(e) => Some(ElementalApplication::new(e, 1.0)),
+            Attribute::Physical => None,
+        };
+        let mut target_stats = StatSheet::from([(StatType::Level, self.enemy.level())]);
+        for &a in Attribute::VARIANTS {
+            let res = self.enemy.res(a) - self.res_reduct.get(&a).copied().unwrap_or(0.0);
+            target_stats.add_stat(&Stat::new(StatType::AttributeRES(a), res));
+        }
+        let target_aura = self.aura.map(|a| GaugedAura::new(a, 1.0, 0.0));
+        evaluate_damage_instance(
+            &self.stats,
+            &talent,
+            elem_app,
+            &target_stats,
+            target_aura.as_ref(),
+            self.crit_mode,
+        )
+    }
+
+    pub fn run(&self, name: &str) -> CaseReport {
+        let mut calc = self.calculator();
+        let reaction =
+            Option::<ElementalReaction>::from_calcindex(calc.get(&L::Reaction.into())).flatten();
+        let breakdown = TRACED
+            .iter()
+            .map(|b| (b.clone(), calc.get(&b.clone().into())))
+            .collect();
+        CaseReport {
+            name: name.to_string(),
+            observed: self.observed,
+            tolerance: self.tolerance,
+            calculated: calc.get(&B::DamageInstanceOutput.into()),
+            legacy: self.legacy_damage(),
+            expected_reaction: self.reaction,
+            reaction,
+            breakdown,
+        }
+    }
+}
+
+/// The results of a case. The breakdown lists the intermediate values of the calculator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseReport {
+    pub name: String,
+    pub observed: f32,
+    pub tolerance: f32,
+    pub calculated: f32,
+    pub legacy: f32,
+    pub expected_reaction: Option<ElementalReaction>,
+    pub reaction: Option<ElementalReaction>,
+    pub breakdown: Vec<(B, f32)>,
+}
+impl CaseReport {
+    pub fn calculated_matches(&self) -> bool {
+        (self.calculated - self.observed).abs() <= self.tolerance
+    }
+
+    pub fn legacy_matches(&self) -> bool {
+        (self.legacy - self.observed).abs() <= self.tolerance
+    }
+
+    pub fn reaction_matches(&self) -> bool {
+        self.reaction == self.expected_reaction
+    }
+
+    /// The value of `b` in the breakdown.
+    pub fn value(&self, b: &B) -> Option<f32> {
+        self.breakdown
+            .iter()
+            .find(|(key, _)| key == b)
+            .map(|&(_, val)| val)
+    }
+
+    /// Whether the calculator got the observed damage and reaction. The legacy damage is only
+    /// reported, as it can't model every case.
+    pub fn passed(&self) -> bool {
+        self.calculated_matches() && self.reaction_matches()
+    }
+}
+
+impl fmt::Display for CaseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.passed() { "ok" } else { "MISMATCH" };
+        let legacy = if self.legacy_matches() { "" } else { " (off)" };
+        writeln!(
+            f,
+            "{}: {status}, observed {} (± {}), calculated {}, legacy {}{legacy}",
+            self.name, self.observed, self.tolerance, self.calculated, self.legacy
+        )?;
+        if !self.reaction_matches() {
+            writeln!(
+                f,
+                "  reaction: observed {:?}, derived {:?}",
+                self.expected_reaction, self.reaction
+            )?;
+        }
+        for (b, val) in self.breakdown.iter() {
+            writeln!(f, "  {b:?}: {val}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
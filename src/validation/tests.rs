@@ -0,0 +1,65 @@
+use crate::{element::reaction::ElementalReaction, B};
+
+use super::{load_cases, run_cases, DEFAULT_TOLERANCE};
+
+/// The Arlecchino melt hit of `main.rs` on crit, worked out by hand rather than recorded in game,
+/// so it checks the calculators against the formulas and each other, not against the game:
+/// 4514.2 * 9 ATK * 2.596 DMG bonus * 190 / 393 DEF * 1.25 RES
+/// * 2 * (1 + 2.78 * 380 / 1780) melt * 2.918 crit = 592736.7
+const MELT_CASE: &str = r#"
+    [arlecchino_melt]
+    stats = { Level = 90, MaxHP = 20626, Atk = 4514.2, Def = 765, ElementalMastery = 380, CritRate = 0.772, CritDmg = 1.918, "DMGMult:Pyro" = 1.416, DMGMult = 0.18 }
+    scaling = { Atk = 9.0 }
+    attribute = "Pyro"
+    category = "NormalAttack"
+    enemy = { level = 103, res = { Pyro = 0.1 } }
+    res_reduct = { Pyro = 0.6 }
+    aura = "Cryo"
+    reaction = "ForwardMelt"
+    crit_mode = "OnCrit"
+    observed = 592736.7
+"#;
+
+#[test]
+fn matching_case() {
+    let cases = load_cases(MELT_CASE).unwrap();
+    assert_eq!(cases["arlecchino_melt"].tolerance, DEFAULT_TOLERANCE);
+    let reports = run_cases(&cases);
+    let report = &reports[0];
+    assert!(report.passed(), "{report}");
+    assert_eq!(report.reaction, Some(ElementalReaction::ForwardMelt));
+    assert!(report.legacy_matches());
+    assert!((report.calculated - report.legacy).abs() <= 0.1);
+    let base_dmg = report.value(&B::BaseDMGFinal).unwrap();
+    assert_eq!((base_dmg * 10.0).round(), 406278.0);
+}
+
+#[test]
+fn mismatched_case() {
+    let text = MELT_CASE
+        .replace("observed = 592736.7", "observed = 250000")
+        .replace("\"ForwardMelt\"", "\"ReverseMelt\"");
+    let cases = load_cases(&text).unwrap();
+    let report = cases["arlecchino_melt"].run("arlecchino_melt");
+    assert!(!report.passed());
+    assert!(!report.calculated_matches() && !report.legacy_matches());
+    assert!(!report.reaction_matches());
+
+    let text = report.to_string();
+    assert!(text.starts_with("arlecchino_melt: MISMATCH"));
+    assert!(text.contains("reaction: observed Some(ReverseMelt), derived Some(ForwardMelt)"));
+    assert!(text.contains("AmpRxnMult: 3.18"));
+}
+
+#[test]
+fn legacy_only_reported() {
+    // The legacy damage calculator has no DEF shred, so it is off, but the case still passes.
+    let text = MELT_CASE
+        .replace("observed = 592736.7", "observed = 701431.9")
+        .replace("crit_mode", "def_reduct = 0.3\n    crit_mode");
+    let cases = load_cases(&text).unwrap();
+    let report = cases["arlecchino_melt"].run("arlecchino_melt");
+    assert!(report.passed(), "{report}");
+    assert!(!report.legacy_matches());
+    assert!(report.to_string().contains(" (off)"));
+}